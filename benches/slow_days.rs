@@ -0,0 +1,46 @@
+use std::fs::File;
+use std::io::prelude::*;
+use std::io::BufReader;
+
+use aoc::days::{day11, day14, day15, day9};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+fn bench_day9(c: &mut Criterion) {
+    c.bench_function("day9 play", |b| {
+        b.iter(|| {
+            let mut game = day9::Game::new(black_box(459));
+            game.play(black_box(71_320 * 100));
+            game.high_score()
+        })
+    });
+}
+
+fn bench_day11(c: &mut Criterion) {
+    c.bench_function("day11 max_up_to_power", |b| {
+        b.iter(|| day11::Grid(black_box(3463)).max_up_to_power(black_box(300)))
+    });
+}
+
+fn bench_day14(c: &mut Criterion) {
+    c.bench_function("day14 find_set", |b| {
+        b.iter(|| {
+            let mut recipes = day14::Recipes::new(3, 7);
+            recipes.find_set(black_box(&[9, 3, 9, 6, 0, 1]))
+        })
+    });
+}
+
+fn bench_day15(c: &mut Criterion) {
+    let file = File::open("inputs/day15.txt").expect("Couldn't open day15 input");
+    let lines = BufReader::new(file).lines();
+    let initial = day15::Battle::parse_lines(lines, 200, 3, 3).expect("Couldn't parse day15");
+
+    c.bench_function("day15 complete", |b| b.iter(|| initial.clone().complete()));
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(10);
+    targets = bench_day9, bench_day11, bench_day14, bench_day15
+}
+criterion_main!(benches);
@@ -1,12 +1,9 @@
 #![warn(clippy::all)]
 
 use clap::{App, Arg};
-use std::collections::HashSet;
-use std::fs::File;
-use std::io::prelude::*;
-use std::io::BufReader;
+use std::fs;
 
-fn main() -> std::io::Result<()> {
+fn main() -> Result<(), failure::Error> {
     let matches = App::new("Day 1")
         .arg(
             Arg::with_name("input")
@@ -21,32 +18,9 @@ fn main() -> std::io::Result<()> {
 
     eprintln!("Using input {}", input_path);
 
-    let file = File::open(input_path)?;
-    let buf_reader = BufReader::new(file);
-
-    let mut sum = 0;
-    let mut values = vec![];
-    for (_i, line) in buf_reader.lines().enumerate() {
-        let s = line?;
-        let n = s.trim().parse::<i64>().unwrap();
-        sum += n;
-        values.push(n);
-    }
-
+    let (sum, repeated) = aoc::days::day1::solve(&fs::read_to_string(input_path)?)?;
     println!("Final sum: {}", sum);
-
-    let mut seen: HashSet<i64> = HashSet::new();
-    sum = 0;
-    'outer: loop {
-        for &v in &values {
-            sum += v;
-            if seen.contains(&sum) {
-                println!("Repeated: {}", sum);
-                break 'outer;
-            }
-            seen.insert(sum);
-        }
-    }
+    println!("Repeated: {}", repeated);
 
     Ok(())
 }
@@ -1,5 +1,6 @@
 #![warn(clippy::all)]
 
+use aoc::geom::BoundingBox;
 use aoc::parse::{convert_err, parse_integer};
 
 use clap::{App, Arg};
@@ -68,22 +69,8 @@ impl Stars {
         self.advance(1)
     }
 
-    fn minimals(&self) -> Option<(i64, i64, i64, i64)> {
-        let Stars(ref stars) = self;
-        if stars.is_empty() {
-            return None;
-        }
-        let (sx, sy) = stars[0].position;
-        let (mut x_min, mut x_max, mut y_min, mut y_max) = (sx, sx, sy, sy);
-        for s in stars {
-            let (x, y) = s.position;
-            x_min = x_min.min(x);
-            x_max = x_max.max(x);
-            y_min = y_min.min(y);
-            y_max = y_max.max(y);
-        }
-
-        Some((x_min, x_max, y_min, y_max))
+    fn minimals(&self) -> Option<BoundingBox> {
+        BoundingBox::from_points(self.0.iter().map(|s| s.position))
     }
 
     fn area(&self) -> i64 {
@@ -91,39 +78,50 @@ impl Stars {
         if stars.len() <= 1 {
             return 1;
         }
-        let (x_min, x_max, y_min, y_max) = self.minimals().unwrap();
+        let bbox = self.minimals().unwrap();
 
-        (x_max + 1 - x_min) * (y_max + 1 - y_min)
+        bbox.width() * bbox.height()
     }
 
     fn minimize(&mut self) -> i64 {
+        // The area is positive and strictly decreasing until the minimum, so
+        // this always terminates
+        self.minimize_within(i64::MAX)
+            .expect("Area cannot shrink forever")
+    }
+
+    /// Like `minimize`, but gives up after `max_steps` steps. Returns `None`
+    /// (with the stars back at their starting positions) if the area is still
+    /// shrinking at that point.
+    fn minimize_within(&mut self, max_steps: i64) -> Option<i64> {
         let mut last = self.area();
-        let mut steps = 0;
-        loop {
+        for steps in 0..max_steps {
             self.step();
-            steps += 1;
             let area = self.area();
             if area >= last {
-                break;
+                self.advance(-1);
+                return Some(steps);
             }
             last = area;
         }
-        steps -= 1;
-        self.advance(-1);
 
-        steps
+        self.advance(-max_steps);
+        None
     }
 
+    /// Draw the stars in their bounding box, one string per row. With no stars, there's nothing
+    /// to draw; stars all in one place are a single `#`.
     fn to_strings(&self) -> Vec<String> {
-        if self.0.is_empty() {
-            return vec![];
-        }
-        let (x_min, x_max, y_min, y_max) = self.minimals().unwrap();
-        let (w, h) = ((x_max - x_min + 1) as usize, (y_max - y_min + 1) as usize);
+        let bbox = match self.minimals() {
+            None => return vec![],
+            Some(b) => b,
+        };
+        let (x_min, y_min) = bbox.min;
+        let (w, h) = (bbox.width() as usize, bbox.height() as usize);
 
-        let row: String = std::iter::repeat('.').take(w).collect();
+        let row: String = ".".repeat(w);
 
-        let mut strings: Vec<String> = std::iter::repeat(row).take(h).collect();
+        let mut strings: Vec<String> = vec![row; h];
         for s in &self.0 {
             let (x, y) = s.position;
             let (row, col) = ((x - x_min) as usize, (y - y_min) as usize);
@@ -131,6 +129,37 @@ impl Stars {
         }
         strings
     }
+
+    /// Render the stars as white pixels on a black background, with each cell
+    /// drawn as a `scale` x `scale` square.
+    #[cfg(feature = "image")]
+    fn to_image(&self, scale: u32) -> image::GrayImage {
+        let bbox = match self.minimals() {
+            Some(m) => m,
+            None => return image::GrayImage::new(0, 0),
+        };
+        let (x_min, y_min) = bbox.min;
+        let (w, h) = (bbox.width() as u32, bbox.height() as u32);
+
+        let mut img = image::GrayImage::new(w * scale, h * scale);
+        for s in &self.0 {
+            let (x, y) = s.position;
+            let (px, py) = ((x - x_min) as u32 * scale, (y - y_min) as u32 * scale);
+            for dx in 0..scale {
+                for dy in 0..scale {
+                    img.put_pixel(px + dx, py + dy, image::Luma([255]));
+                }
+            }
+        }
+        img
+    }
+
+    /// Advance to the time the message appears, returning that time and the
+    /// rendered message.
+    fn message_at(&mut self) -> (i64, Vec<String>) {
+        let steps = self.minimize();
+        (steps, self.to_strings())
+    }
 }
 
 fn main() -> Result<(), failure::Error> {
@@ -142,6 +171,13 @@ fn main() -> Result<(), failure::Error> {
                 .value_name("INPUT")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("png")
+                .long("png")
+                .value_name("PNG")
+                .takes_value(true)
+                .help("Save the message as a PNG (requires the 'image' feature)"),
+        )
         .get_matches();
 
     let input_path = matches.value_of("INPUT").unwrap_or("inputs/day10.txt");
@@ -154,11 +190,22 @@ fn main() -> Result<(), failure::Error> {
     let mut stars = Stars::parse_lines(buf_reader.lines())?;
 
     println!("Found stars: {}", stars.0.len());
-    let steps = stars.minimize();
-    println!("Minimized after {} steps:", steps);
-    for s in &stars.to_strings() {
+    let (steps, message) = stars.message_at();
+    println!("Message:");
+    for s in &message {
         println!("{}", s);
     }
+    println!("Seconds to wait for message: {}", steps);
+
+    if let Some(png_path) = matches.value_of("png") {
+        #[cfg(feature = "image")]
+        stars.to_image(8).save(png_path)?;
+        #[cfg(not(feature = "image"))]
+        eprintln!(
+            "Cannot save {}: built without the 'image' feature",
+            png_path
+        );
+    }
 
     Ok(())
 }
@@ -273,4 +320,118 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn test_minimize_within() {
+        let parsed = Stars::parse_lines::<_, failure::Error, _>(TEST_STARS.iter().map(Ok));
+        let mut stars = parsed.expect("Parse error");
+        let start = stars.clone();
+        assert_eq!(stars.minimize_within(2), None);
+        assert_eq!(stars, start);
+        assert_eq!(stars.minimize_within(4), Some(3));
+        assert_eq!(stars.area(), 10 * 8);
+
+        let mut apart = Stars(vec![
+            Star {
+                position: (0, 0),
+                velocity: (-1, -1),
+            },
+            Star {
+                position: (1, 1),
+                velocity: (1, 1),
+            },
+        ]);
+        let start = apart.clone();
+        assert_eq!(apart.minimize_within(100), Some(0));
+        assert_eq!(apart, start);
+
+        let mut still = Stars(vec![
+            Star {
+                position: (0, 0),
+                velocity: (0, 0),
+            },
+            Star {
+                position: (3, 1),
+                velocity: (0, 0),
+            },
+        ]);
+        let start = still.clone();
+        assert_eq!(still.minimize_within(100), Some(0));
+        assert_eq!(still, start);
+    }
+
+    #[test]
+    fn test_degenerate_drawings() {
+        let mut none = Stars(vec![]);
+        assert_eq!(none.minimize(), 0);
+        assert_eq!(none.to_strings(), Vec::<String>::new());
+
+        let mut one = Stars(vec![Star {
+            position: (-4, 7),
+            velocity: (1, -1),
+        }]);
+        assert_eq!(one.minimize(), 0);
+        assert_eq!(one.to_strings(), vec!["#"]);
+
+        // Two stars moving together in the same place
+        let star = Star {
+            position: (3, 3),
+            velocity: (2, 0),
+        };
+        let mut together = Stars(vec![star.clone(), star]);
+        assert_eq!(together.minimize(), 0);
+        assert_eq!(together.to_strings(), vec!["#"]);
+
+        // Two stars that meet after 2 steps
+        let mut meeting = Stars(vec![
+            Star {
+                position: (0, 0),
+                velocity: (1, 1),
+            },
+            Star {
+                position: (4, 2),
+                velocity: (-1, 0),
+            },
+        ]);
+        assert_eq!(meeting.message_at(), (2, vec!["#".to_owned()]));
+    }
+
+    #[test]
+    fn test_message_at() {
+        let parsed = Stars::parse_lines::<_, failure::Error, _>(TEST_STARS.iter().map(Ok));
+        let mut stars = parsed.expect("Parse error");
+        let (time, message) = stars.message_at();
+        assert_eq!(time, 3);
+        assert_eq!(
+            message,
+            vec![
+                "#...#..###",
+                "#...#...#.",
+                "#...#...#.",
+                "#####...#.",
+                "#...#...#.",
+                "#...#...#.",
+                "#...#...#.",
+                "#...#..###",
+            ]
+        );
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn test_to_image() {
+        let parsed = Stars::parse_lines::<_, failure::Error, _>(TEST_STARS.iter().map(Ok));
+        let mut stars = parsed.expect("Parse error");
+        stars.minimize();
+
+        let img = stars.to_image(1);
+        assert_eq!(img.dimensions(), (10, 8));
+        assert_eq!(img.get_pixel(0, 0), &image::Luma([255]));
+        assert_eq!(img.get_pixel(1, 0), &image::Luma([0]));
+
+        let img = stars.to_image(3);
+        assert_eq!(img.dimensions(), (30, 24));
+        assert_eq!(img.get_pixel(2, 2), &image::Luma([255]));
+        assert_eq!(img.get_pixel(3, 2), &image::Luma([0]));
+    }
 }
@@ -8,6 +8,7 @@ use std::collections::{HashMap, VecDeque};
 use std::fs::File;
 use std::io::prelude::*;
 use std::iter::FromIterator;
+use std::str::FromStr;
 
 #[derive(Debug, Copy, Clone, Hash, PartialEq, PartialOrd, Eq)]
 enum Pot {
@@ -40,21 +41,22 @@ impl Pot {
     }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, PartialOrd)]
+/// A rule mapping a window of `2 * radius + 1` pots, centered on a pot, to the
+/// next state of that pot.
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
 struct PropagationRule {
-    input: [Pot; 5],
+    input: Vec<Pot>,
     output: Pot,
 }
 
 impl PropagationRule {
-    fn parser<I>() -> impl combine::Parser<Input = I, Output = Self>
+    fn parser<I>(width: usize) -> impl combine::Parser<Input = I, Output = Self>
     where
         I: combine::Stream<Item = char>,
         // Necessary due to rust-lang/rust#24159
         I::Error: combine::ParseError<I::Item, I::Range, I::Position>,
     {
-        let inputs = combine::parser::repeat::count_min_max(5, 5, Pot::parser())
-            .map(|v: Vec<Pot>| [v[0], v[1], v[2], v[3], v[4]]);
+        let inputs = combine::parser::repeat::count_min_max(width, width, Pot::parser());
         let sep = combine::parser::char::string(" => ");
         let output = Pot::parser();
 
@@ -86,8 +88,28 @@ impl PotState {
             .sum()
     }
 
-    fn rule_tuple(&self, ix: isize) -> [Pot; 5] {
-        let mut arr = [Pot::Empty; 5];
+    /// Remove empty pots from either end, so that states that only differ by a
+    /// translation have identical `pots`.
+    fn trim(&mut self) {
+        // Pop any empty ones from the end
+        while let Some(&p) = self.pots.back() {
+            if p.full() {
+                break;
+            }
+            self.pots.pop_back();
+        }
+
+        // Pop any empty ones from the beginning
+        while let Some(&p) = self.pots.front() {
+            if p.full() {
+                break;
+            }
+            self.pots.pop_front();
+            self.start -= 1;
+        }
+    }
+
+    fn rule_tuple(&self, ix: isize, radius: isize) -> Vec<Pot> {
         fn get(ps: &VecDeque<Pot>, j: isize) -> Pot {
             if j >= 0 && j < ps.len() as isize {
                 ps[j as usize]
@@ -95,109 +117,104 @@ impl PotState {
                 Pot::Empty
             }
         }
-        for i in -2..=2isize {
-            arr[(i + 2) as usize] = get(&self.pots, ix + i)
-        }
 
-        arr
+        (-radius..=radius)
+            .map(|i| get(&self.pots, ix + i))
+            .collect()
     }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 struct Pots {
     state: PotState,
-    rules: HashMap<[Pot; 5], Pot>,
+    // Number of pots on either side of the center pot that each rule looks at
+    radius: usize,
+    rules: HashMap<Vec<Pot>, Pot>,
 }
 
 impl Pots {
-    fn new<P, R>(pots: P, rules: R) -> Self
+    fn new<P, R>(pots: P, radius: usize, rules: R) -> Self
     where
         P: Iterator<Item = Pot>,
         R: Iterator<Item = PropagationRule>,
     {
-        let rule_map = HashMap::from_iter(rules.map(|r| (r.input, r.output)));
+        let rule_map = HashMap::from_iter(rules.map(|r| {
+            assert_eq!(r.input.len(), 2 * radius + 1);
+            (r.input, r.output)
+        }));
         Pots {
             state: PotState {
                 pots: pots.collect(),
                 start: 0,
             },
+            radius,
             rules: rule_map,
         }
     }
 
     fn parser<I>() -> impl combine::Parser<Input = I, Output = Self>
+    where
+        I: combine::Stream<Item = char>,
+        I::Error: combine::ParseError<I::Item, I::Range, I::Position>,
+    {
+        Pots::parser_with_radius(2)
+    }
+
+    fn parser_with_radius<I>(radius: usize) -> impl combine::Parser<Input = I, Output = Self>
     where
         I: combine::Stream<Item = char>,
         I::Error: combine::ParseError<I::Item, I::Range, I::Position>,
     {
         let pot = Pot::parser();
         let pots = combine::parser::repeat::many1(pot);
+        let rule = PropagationRule::parser(2 * radius + 1);
 
         (
             c_char::string("initial state: ").with(pots),
-            combine::parser::repeat::many1(c_char::spaces().with(PropagationRule::parser())),
+            // Backtrack over the whitespace before anything that isn't a rule, so it's left over
+            combine::parser::repeat::many1(combine::attempt(c_char::spaces().with(rule))),
         )
-            .map(|(pots, rules)| {
+            .map(move |(pots, rules)| {
                 let _: Vec<Pot> = pots;
                 let _: Vec<PropagationRule> = rules;
-                Pots::new(pots.into_iter(), rules.into_iter())
+                Pots::new(pots.into_iter(), radius, rules.into_iter())
             })
     }
 
     fn get_rule(&self, ix: isize) -> Pot {
-        let arr = self.state.rule_tuple(ix);
+        let arr = self.state.rule_tuple(ix, self.radius as isize);
         *self.rules.get(&arr).unwrap_or(&Pot::Empty)
     }
 
     fn advance(&mut self) {
-        // Find the first two
-        let first_pair = [self.get_rule(-2), self.get_rule(-1)];
-        let mut last_pair = first_pair;
+        // Each pot within `radius` of the ends can become full, so we add
+        // that many on either side, and then trim any empty ones
+        let radius = self.radius as isize;
         let ln = self.state.pots.len() as isize;
 
-        // transform the existing ones
-        for ix in 0..ln + 2 {
-            let transformed = self.get_rule(ix);
-            if ix >= 2 {
-                self.state.pots[(ix - 2) as usize] = last_pair[0];
-            }
-            last_pair = [last_pair[1], transformed];
-        }
+        let next: VecDeque<Pot> = (-radius..ln + radius).map(|ix| self.get_rule(ix)).collect();
+        self.state.pots = next;
+        self.state.start += radius;
+        self.state.trim();
+    }
+}
 
-        // Add the first two if necessary
-        if first_pair[0].full() || first_pair[1].full() {
-            self.state.pots.push_front(first_pair[1]);
-            self.state.start += 1;
-        }
-        if first_pair[0].full() {
-            self.state.pots.push_front(first_pair[0]);
-            self.state.start += 1;
-        }
+impl FromStr for Pots {
+    type Err = failure::Error;
 
-        // And append the last two, if necessary
-        if last_pair[0].full() || last_pair[1].full() {
-            self.state.pots.push_back(last_pair[0]);
-        }
-        if last_pair[1].full() {
-            self.state.pots.push_back(last_pair[1]);
-        }
+    /// Parse the whole puzzle input, which may only have whitespace around it.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = c_char::spaces().with(Pots::parser());
+        let (pots, rest) = parser
+            .easy_parse(State::new(s))
+            .map_err(|e| failure::format_err!("Couldn't parse pots: {}", e))?;
 
-        // Pop any empty ones from the end
-        while let Some(&p) = self.state.pots.back() {
-            if p.full() {
-                break;
-            }
-            self.state.pots.pop_back();
+        let rest = rest.input.trim();
+        if !rest.is_empty() {
+            failure::bail!("Unexpected input after the rules: {:?}", rest);
         }
 
-        // Pop any empty ones from the beginning
-        while let Some(&p) = self.state.pots.front() {
-            if p.full() {
-                break;
-            }
-            self.state.pots.pop_front();
-            self.state.start -= 1;
-        }
+        Ok(pots)
     }
 }
 
@@ -223,74 +240,91 @@ impl std::fmt::Display for PotState {
 
 struct PotAdvancer {
     pots: Pots,
-    // Pots -> (start, generation)
+    // Trimmed pots -> (start, generation)
     seen: HashMap<VecDeque<Pot>, (isize, isize)>,
-    repeats: Vec<PotState>,
+    // Every state simulated so far, by generation
+    history: Vec<PotState>,
     // Start_shift, first generation
     first: Option<(isize, isize)>,
     index: isize,
 }
 
 impl PotAdvancer {
-    fn new(p: Pots) -> Self {
+    fn new(mut p: Pots) -> Self {
+        // Pots::advance always trims, so we trim the initial state as well to
+        // recognize a repeat of it
+        p.state.trim();
         let mut seen = HashMap::new();
         seen.insert(p.state.pots.clone(), (p.state.start, 0));
         PotAdvancer {
             pots: p.clone(),
             seen,
-            repeats: vec![p.state],
+            history: vec![p.state],
             first: None,
             index: 0,
         }
     }
 
     fn advance(&mut self, dist: isize) {
-        let target_ix = self.index + dist;
-        while self.index != target_ix && self.first.is_none() {
+        self.index += dist;
+        self.pots.state = self.state_at(self.index);
+    }
+
+    /// The sum of the indices of the full pots at the given generation.
+    ///
+    /// This simulates as far as needed to find a repeat, but does not change
+    /// the current generation.
+    fn index_sum_at(&mut self, generation: isize) -> i64 {
+        self.state_at(generation).index_sum()
+    }
+
+    fn state_at(&mut self, generation: isize) -> PotState {
+        while generation >= self.history.len() as isize && self.first.is_none() {
             self.simple_step();
         }
-        if self.index == target_ix {
-            return;
+        if generation < self.history.len() as isize {
+            return self.history[generation as usize].clone();
         }
 
-        self.index = target_ix;
+        // The pattern repeats every `len` generations, shifted by
+        // `start_shift` each time
         let (start_shift, first_gen) = self.first.unwrap();
-        let len = self.repeats.len() as isize;
-        let skipped = (self.index - first_gen) / len;
-        let r_ix = (self.index - first_gen) % len;
-        let mut state = self.repeats[r_ix as usize].clone();
+        let len = self.history.len() as isize - first_gen;
+        let skipped = (generation - first_gen) / len;
+        let r_ix = (generation - first_gen) % len;
+        let mut state = self.history[(first_gen + r_ix) as usize].clone();
         state.start += skipped * start_shift;
-        self.pots.state = state;
+        state
     }
 
     fn simple_step(&mut self) {
-        self.index += 1;
+        let generation = self.history.len() as isize;
+        let last = self.history[self.history.len() - 1].clone();
+        let current = std::mem::replace(&mut self.pots.state, last);
         self.pots.advance();
-        let state = self.pots.state.clone();
+        let state = std::mem::replace(&mut self.pots.state, current);
 
-        let (start_ix, generation): (isize, isize) = match self.seen.entry(state.pots) {
+        let (start_ix, first_gen): (isize, isize) = match self.seen.entry(state.pots.clone()) {
             std::collections::hash_map::Entry::Vacant(v) => {
-                v.insert((self.pots.state.start, self.index));
-                self.repeats.push(self.pots.state.clone());
+                v.insert((state.start, generation));
+                self.history.push(state);
                 return;
             }
             std::collections::hash_map::Entry::Occupied(o) => *o.get(),
         };
 
-        let shift = self.pots.state.start - start_ix;
+        let shift = state.start - start_ix;
         println!(
             "Found repeat at indices {} - {} with shift {}",
-            generation, self.index, shift,
+            first_gen, generation, shift,
         );
 
-        let just_repeats = Vec::from_iter(self.repeats.drain((generation as usize)..));
-        self.repeats = just_repeats;
-        self.first = Some((shift, generation));
+        self.first = Some((shift, first_gen));
         self.seen.clear();
     }
 }
 
-fn main() -> std::io::Result<()> {
+fn main() -> Result<(), failure::Error> {
     let matches = App::new("Day 12")
         .arg(
             Arg::with_name("input")
@@ -308,11 +342,7 @@ fn main() -> std::io::Result<()> {
     let mut contents = String::new();
     let mut file = File::open(input_path)?;
     file.read_to_string(&mut contents)?;
-    let s: &str = contents.as_ref();
-    let stream = State::new(s);
-
-    let mut parser = c_char::spaces().with(Pots::parser());
-    let (mut pots, _) = parser.easy_parse(stream).unwrap();
+    let pots: Pots = contents.parse()?;
 
     println!(
         "Parsed {} pots and {} rules",
@@ -320,17 +350,15 @@ fn main() -> std::io::Result<()> {
         pots.rules.len()
     );
 
+    let mut a = PotAdvancer::new(pots);
     for _ in 0..20 {
-        pots.advance();
-        println!("{}", pots.state);
+        a.advance(1);
+        println!("{}", a.pots.state);
     }
-    println!("Index sum: {}", pots.state.index_sum());
-
-    let mut a = PotAdvancer::new(pots);
-
-    a.advance(50_000_000_000 - 20);
     println!("Index sum: {}", a.pots.state.index_sum());
 
+    println!("Index sum: {}", a.index_sum_at(50_000_000_000));
+
     Ok(())
 }
 
@@ -369,6 +397,27 @@ initial state: #..#.#..##......###...###
         assert_eq!(pots.rules.len(), 14);
     }
 
+    #[test]
+    fn test_from_str() {
+        let pots = Pots::from_str(TEST_INPUT).unwrap();
+        assert_eq!(pots.state.pots.len(), 25);
+        assert_eq!(pots.rules.len(), 14);
+
+        let padded = format!("\n{}\n\n  \n", TEST_INPUT);
+        assert_eq!(Pots::from_str(&padded).unwrap(), pots);
+
+        let stray = format!("{}\n#.#.# => x\n", TEST_INPUT);
+        let err = Pots::from_str(&stray).unwrap_err();
+        assert!(err.to_string().contains("#.#.# => x"), "{}", err);
+
+        let stray = format!("{}\nthe end", TEST_INPUT);
+        let err = Pots::from_str(&stray).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Unexpected input after the rules: \"the end\""
+        );
+    }
+
     #[test]
     fn test_advance() {
         println!(r##"Test output: r#"{}"#"##, TEST_INPUT);
@@ -385,4 +434,92 @@ initial state: #..#.#..##......###...###
         assert_eq!(state_str, "#.|...##....#####...#######....#.#..##");
         assert_eq!(pots.state.index_sum(), 325);
     }
+
+    #[test]
+    fn test_radius_one() {
+        // Every plant moves one pot to the right each generation
+        let input = r#"
+initial state: #..##
+
+#.. => #
+#.# => #
+##. => #
+### => #"#;
+
+        let mut parser = c_char::spaces().with(Pots::parser_with_radius(1));
+        let (mut pots, _) = parser.easy_parse(State::new(input)).unwrap();
+        assert_eq!(pots.radius, 1);
+        assert_eq!(pots.rules.len(), 4);
+        assert_eq!(pots.state.index_sum(), 7);
+
+        for _ in 0..3 {
+            pots.advance();
+        }
+
+        assert_eq!(format!("{}", pots.state), "|(3)#..##");
+        assert_eq!(pots.state.index_sum(), 7 + 3 * 3);
+    }
+
+    #[test]
+    fn test_advancer() {
+        let mut parser = c_char::spaces().with(Pots::parser());
+        let (pots, _) = parser.easy_parse(State::new(TEST_INPUT)).unwrap();
+
+        // Simulate long enough to reach the steady state, and extrapolate
+        // linearly from there
+        let mut simulated = pots.clone();
+        let mut sums = vec![simulated.state.index_sum()];
+        for _ in 0..200 {
+            simulated.advance();
+            sums.push(simulated.state.index_sum());
+        }
+        let per_gen = sums[200] - sums[199];
+        let remaining = 50_000_000_000 - 200;
+        let expected = sums[200] + per_gen * remaining;
+
+        let mut a = PotAdvancer::new(pots.clone());
+        a.advance(20);
+        assert_eq!(a.pots.state.index_sum(), 325);
+        a.advance(50_000_000_000 - 20);
+        assert_eq!(a.pots.state.index_sum(), expected);
+
+        // An initial state with empty margins is only a translation of itself
+        let mut padded = pots;
+        padded.state.pots.push_front(Pot::Empty);
+        padded.state.pots.push_back(Pot::Empty);
+        padded.state.start += 1;
+        let mut a = PotAdvancer::new(padded);
+        a.advance(50_000_000_000);
+        assert_eq!(a.pots.state.index_sum(), expected);
+    }
+
+    #[test]
+    fn test_index_sum_at() {
+        let mut parser = c_char::spaces().with(Pots::parser());
+        let (pots, _) = parser.easy_parse(State::new(TEST_INPUT)).unwrap();
+
+        let mut simulated = pots.clone();
+        for _ in 0..100 {
+            simulated.advance();
+        }
+
+        let mut a = PotAdvancer::new(pots);
+        let (s20, s100, s101, s1000) = (
+            a.index_sum_at(20),
+            a.index_sum_at(100),
+            a.index_sum_at(101),
+            a.index_sum_at(1000),
+        );
+        assert_eq!(s20, 325);
+        assert_eq!(s100, simulated.state.index_sum());
+        // Once in the steady state, the sum grows linearly
+        assert_eq!(s1000 - s100, (s101 - s100) * 900);
+
+        // The advancer itself stays put
+        assert_eq!(a.index, 0);
+        assert_eq!(a.index_sum_at(0), a.pots.state.index_sum());
+        a.advance(1000);
+        assert_eq!(a.pots.state.index_sum(), s1000);
+        assert_eq!(a.index_sum_at(20), 325);
+    }
 }
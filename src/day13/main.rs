@@ -5,7 +5,6 @@ use std::collections::{HashMap, HashSet};
 use std::fs::File;
 use std::io::prelude::*;
 use std::io::BufReader;
-use std::iter::FromIterator;
 
 #[derive(Debug, Copy, Clone, Hash, PartialEq, PartialOrd, Eq, Ord)]
 enum Track {
@@ -80,11 +79,38 @@ impl Turn {
     }
 }
 
+/// How a cart chooses which way to go at each intersection.
+#[derive(Debug, Default, Copy, Clone, Hash, PartialEq, PartialOrd, Eq, Ord)]
+enum TurnStrategy {
+    /// Left, then straight, then right, then around again, as in the puzzle.
+    #[default]
+    Cycle,
+    /// The same way every time.
+    Always(Turn),
+}
+
+impl TurnStrategy {
+    fn first(self) -> Turn {
+        match self {
+            TurnStrategy::Cycle => Turn::Left,
+            TurnStrategy::Always(t) => t,
+        }
+    }
+
+    fn after(self, turn: Turn) -> Turn {
+        match self {
+            TurnStrategy::Cycle => turn.next(),
+            TurnStrategy::Always(t) => t,
+        }
+    }
+}
+
 #[derive(Debug, Copy, Clone, Hash, PartialEq, PartialOrd, Eq, Ord)]
 struct Cart {
     loc: (i64, i64),
     direction: Direction,
     next_turn: Turn,
+    strategy: TurnStrategy,
 }
 
 impl Cart {
@@ -93,6 +119,16 @@ impl Cart {
             loc,
             direction,
             next_turn,
+            strategy: TurnStrategy::default(),
+        }
+    }
+
+    fn with_strategy(loc: (i64, i64), direction: Direction, strategy: TurnStrategy) -> Self {
+        Cart {
+            loc,
+            direction,
+            next_turn: strategy.first(),
+            strategy,
         }
     }
 
@@ -143,16 +179,35 @@ impl Cart {
             (Direction::Right, Track::DiagonalDown) => self.direction = Direction::Down,
             (d, Track::Intersection) => {
                 self.direction = self.next_turn.apply(d);
-                self.next_turn = self.next_turn.next();
+                self.next_turn = self.strategy.after(self.next_turn);
             }
         }
     }
 }
 
-#[derive(Debug)]
+/// The results of running a `Railway` until at most one cart is left.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct RunResult {
+    // None if there were never any collisions
+    first_collision: Option<(i64, i64)>,
+    // The tick on which the first collision occurred, starting from 1
+    first_tick: usize,
+    // None if the last carts all crashed into each other
+    last_cart: Option<(i64, i64)>,
+    // The tick after which at most one cart remained, or the run stopped
+    last_tick: usize,
+    // More than one if the run stopped before the carts were done crashing
+    carts_left: usize,
+}
+
+#[derive(Debug, Clone)]
 struct Railway {
     tracks: HashMap<(i64, i64), Track>,
     carts: Vec<Cart>,
+    // Number of steps taken so far
+    tick: usize,
+    // (tick, location) of every collision so far
+    collisions: Vec<(usize, (i64, i64))>,
 }
 
 impl Railway {
@@ -202,36 +257,78 @@ impl Railway {
             }
         }
 
-        Ok(Railway { tracks, carts })
+        Ok(Railway {
+            tracks,
+            carts,
+            tick: 0,
+            collisions: Vec::new(),
+        })
+    }
+
+    /// Every collision so far, as `(tick, location)`, with ticks starting
+    /// from 1.
+    fn collision_log(&self) -> &[(usize, (i64, i64))] {
+        &self.collisions
     }
 
-    fn step(&mut self) -> Vec<(i64, i64)> {
+    /// Move every cart one square, returning where any collisions happened. Errors if a cart
+    /// runs off the track, which can only happen on a malformed map.
+    fn step(&mut self) -> Result<Vec<(i64, i64)>, failure::Error> {
+        self.tick += 1;
         self.carts.sort();
-        // location -> cart index
-        let mut occupied: HashMap<(i64, i64), usize> = HashMap::with_capacity(self.carts.len());
+        // location -> indices of carts there
+        let mut occupied: HashMap<(i64, i64), Vec<usize>> =
+            HashMap::with_capacity(self.carts.len());
         let mut to_remove: HashSet<usize> = HashSet::new();
+        let mut collisions = Vec::new();
 
         for (i, c) in self.carts.iter().enumerate() {
-            if let Some(_j) = occupied.insert(c.loc, i) {
-                panic!("Collision not removed at ({}, {})", c.loc.0, c.loc.1);
+            occupied.entry(c.loc).or_default().push(i);
+        }
+
+        // Carts that started out sharing a cell have already collided
+        for (i, c) in self.carts.iter().enumerate() {
+            let here = &occupied[&c.loc];
+            if here.len() > 1 && here[0] == i {
+                to_remove.extend(here.iter().cloned());
+                collisions.push(c.loc);
             }
         }
+        occupied.retain(|_, here| here.len() == 1);
 
-        let mut collisions = Vec::new();
         for (i, c) in self.carts.iter_mut().enumerate() {
+            if to_remove.contains(&i) {
+                // Crashed carts stop moving immediately
+                continue;
+            }
             occupied.remove(&c.loc);
             c.step();
-            if let Some(j) = occupied.insert(c.loc, i) {
+            if let Some(others) = occupied.remove(&c.loc) {
+                // Everything in this cell is removed, leaving it empty
                 to_remove.insert(i);
-                to_remove.insert(j);
+                to_remove.extend(others);
                 collisions.push(c.loc);
+                continue;
             }
-            let new_track = self.tracks[&c.loc];
+            occupied.insert(c.loc, vec![i]);
+            let new_track = match self.tracks.get(&c.loc) {
+                Some(&t) => t,
+                None => failure::bail!(
+                    "Cart moving {:?} ran off the track at {:?} on tick {}",
+                    c.direction,
+                    c.loc,
+                    self.tick
+                ),
+            };
             c.turn(new_track);
         }
 
+        for &loc in &collisions {
+            self.collisions.push((self.tick, loc));
+        }
+
         if to_remove.is_empty() {
-            return collisions;
+            return Ok(collisions);
         }
 
         // Remove anything involved in a collision
@@ -249,24 +346,60 @@ impl Railway {
             .collect();
         self.carts = new_carts;
 
-        collisions
+        Ok(collisions)
     }
-}
 
-impl std::fmt::Display for Railway {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        let (mut max_x, mut max_y) = (0, 0);
-        for &(x, y) in self.tracks.keys() {
-            max_x = max_x.max(x);
-            max_y = max_y.max(y);
+    /// Render the railway before the first tick and after each tick, until
+    /// either at most one cart is left or `max_ticks` ticks have passed.
+    fn run_recording(&mut self, max_ticks: usize) -> Result<Vec<String>, failure::Error> {
+        let mut frames = vec![self.to_string()];
+        for _ in 0..max_ticks {
+            if self.carts.len() <= 1 {
+                break;
+            }
+            self.step()?;
+            frames.push(self.to_string());
         }
+        Ok(frames)
+    }
+
+    /// Step until at most one cart is left or tick `max_ticks` is reached,
+    /// noting where the first collision was along the way. Ticks are counted
+    /// from the start, not from when `run` was called.
+    fn run(&mut self, max_ticks: usize) -> Result<RunResult, failure::Error> {
+        while self.carts.len() > 1 && self.tick < max_ticks {
+            self.step()?;
+        }
+
+        let first = self.collision_log().first();
+        let last_cart = match self.carts.as_slice() {
+            [cart] => Some(cart.loc),
+            _ => None,
+        };
+        Ok(RunResult {
+            first_collision: first.map(|&(_, loc)| loc),
+            first_tick: first.map(|&(tick, _)| tick).unwrap_or(0),
+            last_cart,
+            last_tick: self.tick,
+            carts_left: self.carts.len(),
+        })
+    }
+}
+
+impl Railway {
+    /// Render the `w` x `h` window of the railway with top-left corner `(x0, y0)`, one line per
+    /// row, with trailing spaces trimmed. Carts sharing a square are drawn as `X`.
+    fn render_region(&self, x0: i64, y0: i64, w: i64, h: i64) -> String {
+        let (w, h) = (w.max(0), h.max(0));
+        let in_window = |x: i64, y: i64| x >= x0 && x < x0 + w && y >= y0 && y < y0 + h;
 
-        let empty_row: Vec<char> = Vec::from_iter(std::iter::repeat(' ').take(max_x as usize + 1));
-        let mut rows: Vec<Vec<char>> =
-            Vec::from_iter(std::iter::repeat(empty_row).take(max_y as usize + 1));
+        let empty_row: Vec<char> = vec![' '; w as usize];
+        let mut rows: Vec<Vec<char>> = vec![empty_row; h as usize];
 
         for (&(x, y), track) in &self.tracks {
-            rows[y as usize][x as usize] = track.as_char();
+            if in_window(x, y) {
+                rows[(y - y0) as usize][(x - x0) as usize] = track.as_char();
+            }
         }
 
         let mut cart_locs = HashSet::with_capacity(self.carts.len());
@@ -278,15 +411,31 @@ impl std::fmt::Display for Railway {
             } else {
                 'X'
             };
-            rows[y as usize][x as usize] = c;
+            if in_window(x, y) {
+                rows[(y - y0) as usize][(x - x0) as usize] = c;
+            }
         }
 
+        let mut out = String::new();
         for row in rows {
             let s: String = row.into_iter().collect();
-            writeln!(f, "{}", s.trim_end())?;
+            out.push_str(s.trim_end());
+            out.push('\n');
+        }
+
+        out
+    }
+}
+
+impl std::fmt::Display for Railway {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let (mut max_x, mut max_y) = (0, 0);
+        for &(x, y) in self.tracks.keys() {
+            max_x = max_x.max(x);
+            max_y = max_y.max(y);
         }
 
-        Ok(())
+        write!(f, "{}", self.render_region(0, 0, max_x + 1, max_y + 1))
     }
 }
 
@@ -299,6 +448,29 @@ fn main() -> Result<(), failure::Error> {
                 .value_name("INPUT")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("record")
+                .short("r")
+                .long("record")
+                .value_name("RECORD")
+                .takes_value(true)
+                .help("Write the rendered railway after each tick to a file"),
+        )
+        .arg(
+            Arg::with_name("max-ticks")
+                .long("max-ticks")
+                .value_name("MAXTICKS")
+                .takes_value(true)
+                .help("The most ticks to run (default 1000 when recording, else 1000000)"),
+        )
+        .arg(
+            Arg::with_name("always")
+                .long("always")
+                .value_name("TURN")
+                .possible_values(&["left", "straight", "right"])
+                .takes_value(true)
+                .help("Turn the same way at every intersection; carts may then never crash"),
+        )
         .get_matches();
 
     let input_path = matches.value_of("INPUT").unwrap_or("inputs/day13.txt");
@@ -308,6 +480,17 @@ fn main() -> Result<(), failure::Error> {
     let file = File::open(input_path)?;
     let buf_reader = BufReader::new(file);
     let mut railway = Railway::parse_lines(buf_reader.lines())?;
+    if let Some(turn) = matches.value_of("always") {
+        let turn = match turn {
+            "left" => Turn::Left,
+            "straight" => Turn::Straight,
+            "right" => Turn::Right,
+            other => failure::bail!("Unknown turn {:?}; expected left, straight or right", other),
+        };
+        for cart in railway.carts.iter_mut() {
+            *cart = Cart::with_strategy(cart.loc, cart.direction, TurnStrategy::Always(turn));
+        }
+    }
 
     eprintln!(
         "Found {} tracks and {} carts",
@@ -315,26 +498,41 @@ fn main() -> Result<(), failure::Error> {
         railway.carts.len()
     );
 
-    let mut n = 0;
-    let (cx, cy) = loop {
-        n += 1;
-        let collisions = railway.step();
-        if let Some(&c) = collisions.first() {
-            break c;
-        }
+    let max_ticks: Option<usize> = match matches.value_of("max-ticks") {
+        Some(m) => Some(m.parse()?),
+        None => None,
     };
 
-    println!("Collision at ({},{}) after {} steps", cx, cy, n);
-
-    let (cx, cy) = loop {
-        n += 1;
-        let _ = railway.step();
-        if railway.carts.len() <= 1 {
-            break railway.carts.first().unwrap().loc;
+    if let Some(record_path) = matches.value_of("record") {
+        let frames = railway.clone().run_recording(max_ticks.unwrap_or(1000))?;
+        let mut file = File::create(record_path)?;
+        for (tick, frame) in frames.iter().enumerate() {
+            writeln!(file, "-- {} --\n{}", tick, frame)?;
         }
-    };
+        eprintln!("Recorded {} frames to {}", frames.len(), record_path);
+    }
 
-    println!("Last car at ({},{}) after {} steps", cx, cy, n);
+    let result = railway.run(max_ticks.unwrap_or(1_000_000))?;
+
+    match result.first_collision {
+        Some((cx, cy)) => println!(
+            "Collision at ({},{}) after {} steps",
+            cx, cy, result.first_tick
+        ),
+        None => println!("No collisions"),
+    }
+
+    match result.last_cart {
+        Some((cx, cy)) => println!(
+            "Last car at ({},{}) after {} steps",
+            cx, cy, result.last_tick
+        ),
+        None if result.carts_left > 1 => println!(
+            "{} cars still running after {} steps",
+            result.carts_left, result.last_tick
+        ),
+        None => println!("No cars left after {} steps", result.last_tick),
+    }
 
     Ok(())
 }
@@ -376,11 +574,27 @@ mod tests {
         assert_eq!(railway.tracks.len(), 48);
     }
 
+    #[test]
+    fn test_render_region() {
+        let railway = get_test_railway(TEST_INPUT);
+        let window = railway.render_region(5, 1, 5, 5);
+        assert_eq!(window, "  /--\n--+-\\\n  | v\n  \\-+\n----/\n");
+
+        assert_eq!(railway.render_region(0, 0, 5, 1), "/->-\\\n");
+        // Off the edge of the map is blank
+        assert_eq!(railway.render_region(20, 20, 3, 2), "\n\n");
+
+        // The whole map is what Display shows
+        let full: Vec<String> = TEST_INPUT.lines().skip(1).map(str::to_owned).collect();
+        assert_eq!(railway.render_region(0, 0, 13, 6), full.join("\n") + "\n");
+        assert_eq!(railway.render_region(0, 0, 13, 6), format!("{}", railway));
+    }
+
     #[test]
     fn test_advance() {
         let mut railway = get_test_railway(TEST_INPUT);
         println!("{}", railway);
-        assert_eq!(railway.step(), vec![]);
+        assert_eq!(railway.step().unwrap(), vec![]);
         println!("{}", railway);
         assert_eq!(railway.carts.len(), 2);
         assert_eq!(railway.tracks.len(), 48);
@@ -398,7 +612,7 @@ mod tests {
         for i in 0..13 {
             // We should have 13 collision-free steps
             println!("-- {} --\n{}", i, railway);
-            assert_eq!(railway.step(), vec![]);
+            assert_eq!(railway.step().unwrap(), vec![]);
         }
         println!("-- == --\n{}", railway);
 
@@ -411,7 +625,7 @@ mod tests {
         );
 
         // Now we should have a collision
-        let collision = railway.step();
+        let collision = railway.step().unwrap();
         println!("-- XX --\n{}", railway);
         assert_eq!(collision, vec![(7, 3)]);
     }
@@ -421,15 +635,15 @@ mod tests {
         let mut railway = get_test_railway(TEST_INPUT2);
         println!("{}", railway);
         assert_eq!(railway.carts.len(), 9);
-        let collisions = railway.step();
+        let collisions = railway.step().unwrap();
         println!("{}", railway);
         assert_eq!(collisions, vec![(2, 0), (2, 4), (6, 4)]);
         assert_eq!(railway.carts.len(), 3);
-        let collisions = railway.step();
+        let collisions = railway.step().unwrap();
         println!("{}", railway);
         assert_eq!(collisions, vec![]);
         assert_eq!(railway.carts.len(), 3);
-        let collisions = railway.step();
+        let collisions = railway.step().unwrap();
         println!("{}", railway);
         assert_eq!(collisions, vec![(2, 4)]);
         assert_eq!(
@@ -437,4 +651,169 @@ mod tests {
             vec![Cart::new((6, 4), Direction::Up, Turn::Left)]
         );
     }
+
+    #[test]
+    fn test_run() {
+        let mut railway = get_test_railway(TEST_INPUT);
+        let result = railway.run(1000).unwrap();
+        assert_eq!(result.first_collision, Some((7, 3)));
+        assert_eq!(result.first_tick, 14);
+        assert_eq!(result.last_cart, None);
+
+        let mut railway = get_test_railway(TEST_INPUT2);
+        let result = railway.run(1000).unwrap();
+        assert_eq!(result.first_collision, Some((2, 0)));
+        assert_eq!(result.first_tick, 1);
+        assert_eq!(result.last_cart, Some((6, 4)));
+        assert_eq!(result.last_tick, 3);
+        assert_eq!(result.carts_left, 1);
+
+        // Carts following each other around a loop never crash, so the run stops at the limit
+        let mut railway = get_test_railway(
+            r#"
+/->->-\
+|     |
+\-----/"#,
+        );
+        let result = railway.run(50).unwrap();
+        assert_eq!(result.first_collision, None);
+        assert_eq!(result.last_cart, None);
+        assert_eq!(result.last_tick, 50);
+        assert_eq!(result.carts_left, 2);
+    }
+
+    #[test]
+    fn test_collision_log() {
+        let mut railway = get_test_railway(TEST_INPUT2);
+        assert_eq!(railway.collision_log(), &[]);
+        railway.run(1000).unwrap();
+        assert_eq!(
+            railway.collision_log(),
+            &[(1, (2, 0)), (1, (2, 4)), (1, (6, 4)), (3, (2, 4))]
+        );
+    }
+
+    #[test]
+    fn test_three_way_collision() {
+        // The first two carts to reach the intersection crash, and the third
+        // passes through the now-empty cell
+        let mut railway = get_test_railway(
+            r#"
+ v
+>+<
+ |"#,
+        );
+        assert_eq!(railway.carts.len(), 3);
+        let collisions = railway.step().unwrap();
+        assert_eq!(collisions, vec![(1, 1)]);
+        assert_eq!(
+            railway.carts,
+            vec![Cart::new((1, 1), Direction::Down, Turn::Straight)]
+        );
+
+        // Three carts sharing a cell are all removed together
+        let mut railway = get_test_railway(TEST_INPUT);
+        let cart = Cart::new((2, 0), Direction::Right, Turn::Left);
+        railway.carts.push(cart);
+        railway.carts.push(cart);
+        assert_eq!(railway.carts.len(), 4);
+        let collisions = railway.step().unwrap();
+        assert_eq!(collisions, vec![(2, 0)]);
+        assert_eq!(railway.carts.len(), 1);
+        assert_eq!(railway.collision_log(), &[(1, (2, 0))]);
+    }
+
+    #[test]
+    fn test_off_track() {
+        let mut railway = get_test_railway(
+            r#"
+/->
+|"#,
+        );
+        let err = railway.step().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Cart moving Right ran off the track at (3, 0) on tick 1"
+        );
+
+        // Running stops at the first cart to leave the track
+        let mut railway = get_test_railway("\n>- <-");
+        let err = railway.run(1000).unwrap_err();
+        assert!(err.to_string().contains("at (2, 0)"), "{}", err);
+    }
+
+    #[test]
+    fn test_turn_strategy() {
+        let cross = r#"
+ |
+>+-
+ |"#;
+
+        // By default, the first intersection is a left turn
+        let mut railway = get_test_railway(cross);
+        railway.step().unwrap();
+        assert_eq!(
+            railway.carts,
+            vec![Cart::new((1, 1), Direction::Up, Turn::Straight)]
+        );
+
+        let mut railway = get_test_railway(cross);
+        let straight = TurnStrategy::Always(Turn::Straight);
+        railway.carts = vec![Cart::with_strategy((0, 1), Direction::Right, straight)];
+        railway.step().unwrap();
+        railway.step().unwrap();
+        assert_eq!(
+            railway.carts,
+            vec![Cart::with_strategy((2, 1), Direction::Right, straight)]
+        );
+
+        let mut cart = Cart::with_strategy((0, 0), Direction::Up, straight);
+        for _ in 0..5 {
+            cart.turn(Track::Intersection);
+            assert_eq!(cart.direction, Direction::Up);
+        }
+
+        let mut cart = Cart::with_strategy((0, 0), Direction::Up, TurnStrategy::Cycle);
+        assert_eq!(cart, Cart::new((0, 0), Direction::Up, Turn::Left));
+        let mut directions = vec![];
+        for _ in 0..4 {
+            cart.turn(Track::Intersection);
+            directions.push(cart.direction);
+        }
+        assert_eq!(
+            directions,
+            vec![
+                Direction::Left,
+                Direction::Left,
+                Direction::Up,
+                Direction::Left
+            ]
+        );
+    }
+
+    #[test]
+    fn test_run_recording() {
+        let mut railway = get_test_railway(TEST_INPUT);
+        let initial = railway.to_string();
+        let frames = railway.run_recording(5).unwrap();
+        assert_eq!(frames.len(), 6);
+        assert_eq!(frames[0], initial);
+        assert_eq!(
+            frames[0],
+            r#"/->-\
+|   |  /----\
+| /-+--+-\  |
+| | |  | v  |
+\-+-/  \-+--/
+  \------/
+"#
+        );
+        assert_eq!(frames[5], railway.to_string());
+
+        // Recording stops once the carts have crashed
+        let mut railway = get_test_railway(TEST_INPUT);
+        let frames = railway.run_recording(100).unwrap();
+        assert_eq!(frames.len(), 15);
+        assert!(railway.carts.is_empty());
+    }
 }
@@ -3,6 +3,7 @@
 use aoc::device::{Instruction, OpCode, Register, Value};
 
 use clap::{App, Arg};
+use failure::Fail;
 use text_io::try_scan;
 
 use std::collections::{HashMap, HashSet, VecDeque};
@@ -52,6 +53,15 @@ impl Triplet {
     }
 }
 
+/// The number of samples that behave like at least `n` opcodes. With `n = 3`, this is the
+/// answer to part 1.
+fn samples_matching_at_least(triplets: &[Triplet], n: usize) -> usize {
+    triplets
+        .iter()
+        .filter(|t| t.matching_codes().len() >= n)
+        .count()
+}
+
 #[allow(clippy::cognitive_complexity)]
 fn parse_triplet(lines: &mut VecDeque<String>) -> Result<Triplet, failure::Error> {
     let l0 = match lines.front() {
@@ -86,14 +96,34 @@ fn parse_triplet(lines: &mut VecDeque<String>) -> Result<Triplet, failure::Error
 struct CodeMap(HashMap<usize, OpCode>);
 
 impl CodeMap {
-    pub fn resolve(&self, instr: UnknownInstruction) -> Instruction {
+    pub fn resolve(&self, instr: UnknownInstruction) -> Result<Instruction, ResolveError> {
         let UnknownInstruction(code, a, b, c) = instr;
-        let op = self.0[&code];
-        Instruction(op, a, b, c)
+        let &op = self.0.get(&code).ok_or(ResolveError::UnknownCode(code))?;
+        Ok(Instruction(op, a, b, c))
+    }
+
+    /// Run a program on a zeroed 4-value register, returning the final register.
+    pub fn execute(&self, program: &[UnknownInstruction]) -> Result<Register, ResolveError> {
+        self.execute_from(Register::from_slice(&[0, 0, 0, 0]), program)
+    }
+
+    /// Run a program starting from the given register, returning the final register.
+    pub fn execute_from(
+        &self,
+        initial: Register,
+        program: &[UnknownInstruction],
+    ) -> Result<Register, ResolveError> {
+        let mut r = initial;
+        for &unknown in program {
+            r.apply(self.resolve(unknown)?);
+        }
+        Ok(r)
     }
 }
 
-fn resolve<T: IntoIterator<Item = Triplet>>(triplets: T) -> CodeMap {
+/// For each numeric opcode seen in the samples, the set of `OpCode`s consistent with all of its
+/// samples, before any elimination between opcodes.
+fn analyze<T: IntoIterator<Item = Triplet>>(triplets: T) -> HashMap<usize, HashSet<OpCode>> {
     let mut partially_resolved: HashMap<usize, HashSet<OpCode>> = HashMap::new();
 
     for t in triplets {
@@ -115,6 +145,35 @@ fn resolve<T: IntoIterator<Item = Triplet>>(triplets: T) -> CodeMap {
         }
     }
 
+    partially_resolved
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ResolveError {
+    /// The samples don't pin down every opcode; holds the candidates for each unresolved code.
+    Underdetermined(HashMap<usize, HashSet<OpCode>>),
+    /// A program used a numeric opcode that never appeared in the samples.
+    UnknownCode(usize),
+}
+
+impl std::fmt::Display for ResolveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ResolveError::Underdetermined(unresolved) => {
+                write!(f, "Could not resolve opcodes: {:?}", unresolved)
+            }
+            ResolveError::UnknownCode(code) => {
+                write!(f, "Opcode {} never appeared in the samples", code)
+            }
+        }
+    }
+}
+
+impl Fail for ResolveError {}
+
+fn resolve<T: IntoIterator<Item = Triplet>>(triplets: T) -> Result<CodeMap, ResolveError> {
+    let partially_resolved = analyze(triplets);
+
     let mut resolved: HashMap<usize, OpCode> = HashMap::new();
     let mut set: HashSet<OpCode> = HashSet::new();
     let mut sets = 1;
@@ -154,7 +213,19 @@ fn resolve<T: IntoIterator<Item = Triplet>>(triplets: T) -> CodeMap {
         }
     }
 
-    CodeMap(resolved)
+    let unresolved: HashMap<usize, HashSet<OpCode>> = partially_resolved
+        .into_iter()
+        .filter(|(c, _)| !resolved.contains_key(c))
+        .map(|(c, mut ops)| {
+            ops.retain(|op| !set.contains(op));
+            (c, ops)
+        })
+        .collect();
+    if !unresolved.is_empty() {
+        return Err(ResolveError::Underdetermined(unresolved));
+    }
+
+    Ok(CodeMap(resolved))
 }
 
 fn parse_instructions(lines: &mut VecDeque<String>) -> Result<UnknownInstruction, failure::Error> {
@@ -202,25 +273,16 @@ fn main() -> Result<(), failure::Error> {
     }
 
     let count = triplets.len();
-    let three_or_more = triplets
-        .iter()
-        .filter(|&t| t.matching_codes().len() >= 3)
-        .count();
+    let three_or_more = samples_matching_at_least(&triplets, 3);
     println!("three-or-more: {} / {}", three_or_more, count);
-    let code_map = resolve(triplets);
+    let code_map = resolve(triplets)?;
     println!(
         "Resolved {} codes, and {} instructions",
         code_map.0.len(),
         instructions.len()
     );
 
-    let mut r = Register {
-        values: vec![0, 0, 0, 0],
-    };
-    for unknown in instructions {
-        let instr = code_map.resolve(unknown);
-        r.apply(instr);
-    }
+    let r = code_map.execute(&instructions)?;
 
     println!("Registers: {:?}", r.values);
 
@@ -245,6 +307,157 @@ mod tests {
         assert_eq!(ops, vec![OpCode::AddI, OpCode::MulR, OpCode::SetI]);
     }
 
+    #[test]
+    fn test_analyze() {
+        let triplets = vec![
+            Triplet(
+                Register {
+                    values: vec![3, 2, 1, 1],
+                },
+                UnknownInstruction(9, 2, 1, 2),
+                Register {
+                    values: vec![3, 2, 2, 1],
+                },
+            ),
+            Triplet(
+                Register {
+                    values: vec![0, 4, 2, 5],
+                },
+                UnknownInstruction(3, 1, 2, 0),
+                Register {
+                    values: vec![6, 4, 2, 5],
+                },
+            ),
+        ];
+
+        let analyzed = analyze(triplets.clone());
+        assert_eq!(analyzed.len(), 2);
+        let expected: HashSet<OpCode> = [OpCode::AddI, OpCode::MulR, OpCode::SetI]
+            .iter()
+            .cloned()
+            .collect();
+        assert_eq!(analyzed[&9], expected);
+        assert!(analyzed[&3].contains(&OpCode::AddR));
+
+        // A second sample for the same code narrows down its candidates
+        let mut narrowed = triplets;
+        narrowed.push(Triplet(
+            Register {
+                values: vec![1, 3, 0, 0],
+            },
+            UnknownInstruction(9, 0, 2, 3),
+            Register {
+                values: vec![1, 3, 0, 3],
+            },
+        ));
+        let analyzed = analyze(narrowed);
+        let expected: HashSet<OpCode> = [OpCode::AddI].iter().cloned().collect();
+        assert_eq!(analyzed[&9], expected);
+    }
+
+    #[test]
+    fn test_samples_matching_at_least() {
+        let samples = [
+            // addi, mulr, seti
+            ([3, 2, 1, 1], UnknownInstruction(9, 2, 1, 2), [3, 2, 2, 1]),
+            // addr, addi, borr, bori
+            ([0, 4, 2, 5], UnknownInstruction(3, 1, 2, 0), [6, 4, 2, 5]),
+            // addi, bori
+            ([1, 3, 0, 0], UnknownInstruction(9, 0, 2, 3), [1, 3, 0, 3]),
+            // eqir, eqri, eqrr
+            ([0, 0, 0, 0], UnknownInstruction(1, 0, 0, 0), [1, 0, 0, 0]),
+            // Nothing
+            ([0, 0, 0, 0], UnknownInstruction(1, 0, 0, 0), [7, 0, 0, 0]),
+        ];
+        let triplets: Vec<Triplet> = samples
+            .iter()
+            .map(|(input, instr, output)| {
+                Triplet(
+                    Register::from_slice(input),
+                    *instr,
+                    Register::from_slice(output),
+                )
+            })
+            .collect();
+
+        let sizes: Vec<usize> = triplets.iter().map(|t| t.matching_codes().len()).collect();
+        assert_eq!(sizes, vec![3, 4, 2, 3, 0]);
+
+        assert_eq!(samples_matching_at_least(&triplets, 3), 3);
+        assert_eq!(samples_matching_at_least(&triplets, 4), 1);
+        assert_eq!(samples_matching_at_least(&triplets, 1), 4);
+        assert_eq!(samples_matching_at_least(&triplets, 0), 5);
+        assert_eq!(samples_matching_at_least(&[], 3), 0);
+    }
+
+    #[test]
+    fn test_resolve_underdetermined() {
+        // Codes 4 and 9 could each be any of the same three opcodes
+        let triplets = vec![
+            Triplet(
+                Register {
+                    values: vec![3, 2, 1, 1],
+                },
+                UnknownInstruction(9, 2, 1, 2),
+                Register {
+                    values: vec![3, 2, 2, 1],
+                },
+            ),
+            Triplet(
+                Register {
+                    values: vec![3, 2, 1, 1],
+                },
+                UnknownInstruction(4, 2, 1, 2),
+                Register {
+                    values: vec![3, 2, 2, 1],
+                },
+            ),
+        ];
+
+        match resolve(triplets) {
+            Err(ResolveError::Underdetermined(unresolved)) => {
+                let mut codes: Vec<usize> = unresolved.keys().cloned().collect();
+                codes.sort();
+                assert_eq!(codes, vec![4, 9]);
+                let expected: HashSet<OpCode> = [OpCode::AddI, OpCode::MulR, OpCode::SetI]
+                    .iter()
+                    .cloned()
+                    .collect();
+                assert_eq!(unresolved[&4], expected);
+                assert_eq!(unresolved[&9], expected);
+            }
+            Err(e) => panic!("Expected an underdetermined error, got {}", e),
+            Ok(_) => panic!("Expected an underdetermined error"),
+        }
+    }
+
+    #[test]
+    fn test_execute() {
+        let code_map = CodeMap(
+            vec![(7, OpCode::SetI), (2, OpCode::AddR)]
+                .into_iter()
+                .collect(),
+        );
+        let program = [
+            UnknownInstruction(7, 5, 0, 1),
+            UnknownInstruction(2, 1, 1, 2),
+        ];
+
+        // seti 5 -> r1, then addr r1 + r1 -> r2
+        let r = code_map.execute(&program).unwrap();
+        assert_eq!(r.values, vec![0, 5, 10, 0]);
+
+        let r = code_map
+            .execute_from(Register::from_slice(&[1, 2, 3, 4]), &program)
+            .unwrap();
+        assert_eq!(r.values, vec![1, 5, 10, 4]);
+
+        let unknown = [UnknownInstruction(3, 0, 0, 0)];
+        let err = code_map.execute(&unknown).unwrap_err();
+        assert_eq!(err, ResolveError::UnknownCode(3));
+        assert_eq!(err.to_string(), "Opcode 3 never appeared in the samples");
+    }
+
     #[test]
     fn test_eq() {
         let mut reg = Register {
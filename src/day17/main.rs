@@ -1,19 +1,23 @@
 #![warn(clippy::all)]
 
+use aoc::input;
 use clap::{App, Arg};
 use text_io::try_scan;
 
 use core::ops::RangeInclusive;
-use std::collections::{HashMap, HashSet, VecDeque};
-use std::fs::File;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::io::prelude::*;
-use std::io::BufReader;
 
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 enum Direction {
     Vertical,
     Horizontal,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
 struct Wall {
     direction: Direction,
     loc: i64,
@@ -25,10 +29,15 @@ impl Wall {
         let (dir, loc, dir2, start, end): (String, i64, String, i64, i64);
         try_scan!(line.bytes() => "{}={}, {}={}..{}", dir, loc, dir2, start, end);
 
-        let direction = if dir == "x" {
-            Direction::Horizontal
-        } else {
-            Direction::Vertical
+        let direction = match (dir.as_str(), dir2.as_str()) {
+            ("x", "y") => Direction::Horizontal,
+            ("y", "x") => Direction::Vertical,
+            _ => {
+                return Err(failure::format_err!(
+                    "Expected x=..., y=... or y=..., x=..., found {:?}",
+                    line
+                ))
+            }
         };
 
         Ok(Wall {
@@ -56,6 +65,7 @@ enum Edge {
 }
 
 impl Walls {
+    // Parse walls from lines, as given by aoc::input: trimmed, with no blank lines.
     fn parse_lines<I, S>(lines: I) -> Result<Walls, failure::Error>
     where
         S: AsRef<str>,
@@ -63,14 +73,7 @@ impl Walls {
     {
         let some_walls: Result<Vec<Wall>, failure::Error> = lines
             .into_iter()
-            .filter_map(|l| {
-                let trimmed = l.as_ref().trim();
-                if trimmed.is_empty() {
-                    None
-                } else {
-                    Some(Wall::parse_line(trimmed))
-                }
-            })
+            .map(|l| Wall::parse_line(l.as_ref()))
             .collect();
         let wall_vec: Vec<Wall> = some_walls?;
 
@@ -112,14 +115,30 @@ impl Walls {
         })
     }
 
-    fn to_bytes(&self) -> Vec<Vec<u8>> {
-        let s: Vec<u8> = std::iter::repeat(b'.')
-            .take(((self.right + 1) - (self.left - 1) + 1) as usize)
-            .collect();
+    /// Check that a spring is somewhere water can flow from: within a column of the clay (or
+    /// just beside it), and no lower than the bottom row.
+    fn check_spring(&self, (x, y): (i64, i64)) -> Result<(), failure::Error> {
+        if x < self.left - 1 || x > self.right + 1 {
+            return Err(failure::format_err!(
+                "Spring x={} is outside the clay, which spans x={}..{}",
+                x,
+                self.left - 1,
+                self.right + 1
+            ));
+        }
+        if y < 0 || y > self.bottom {
+            return Err(failure::format_err!(
+                "Spring y={} is outside the scan, which spans y=0..{}",
+                y,
+                self.bottom
+            ));
+        }
+        Ok(())
+    }
 
-        let mut lines: Vec<Vec<u8>> = std::iter::repeat(s)
-            .take((self.bottom + 1) as usize)
-            .collect();
+    fn to_bytes(&self) -> Vec<Vec<u8>> {
+        let s: Vec<u8> = vec![b'.'; ((self.right + 1) - (self.left - 1) + 1) as usize];
+        let mut lines: Vec<Vec<u8>> = vec![s; (self.bottom + 1) as usize];
 
         for &(x, y) in &self.filled {
             let rel_y = y as usize;
@@ -132,6 +151,7 @@ impl Walls {
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Water {
     Flowing,
     Stable,
@@ -142,8 +162,22 @@ pub struct FlowingWater {
     walls: Walls,
     queue: VecDeque<(i64, i64)>,
     seen: HashSet<(i64, i64)>,
+    // The largest y with any water, kept up to date so progress() needn't go over all of it
+    lowest: i64,
 }
 
+/// A checkpoint of a FlowingWater run. The walls aren't included; they come from the input.
+/// JSON maps need string keys, so the water is stored as a list of pairs.
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+struct SavedState {
+    water: Vec<((i64, i64), Water)>,
+    queue: Vec<(i64, i64)>,
+    seen: Vec<(i64, i64)>,
+}
+
+/// A summary of how far a FlowingWater run has got: the bottom of the walls, the lowest water
+/// so far, and the locations still queued, sorted top to bottom.
 pub struct Progress {
     pub bottom: i64,
     pub lowest: i64,
@@ -152,35 +186,57 @@ pub struct Progress {
 
 impl FlowingWater {
     fn new(walls: Walls, start: (i64, i64)) -> Self {
-        let mut water: HashMap<(i64, i64), Water> = HashMap::new();
-        if start.0 >= walls.top {
-            water.insert(start, Water::Flowing);
-        }
         let mut queue = VecDeque::new();
         queue.push_back(start);
 
-        FlowingWater {
-            water,
+        let mut flow = FlowingWater {
+            water: HashMap::new(),
             walls,
             queue,
             seen: Default::default(),
+            lowest: 0,
+        };
+        if start.1 >= flow.walls.top {
+            flow.add_water(start, Water::Flowing);
         }
+        flow
+    }
+
+    fn add_water(&mut self, loc: (i64, i64), water: Water) {
+        self.water.insert(loc, water);
+        self.lowest = self.lowest.max(loc.1);
     }
 
     pub fn progress(&self) -> Progress {
         let mut waters: Vec<(i64, i64)> = self.queue.iter().copied().collect();
         waters.sort_by_key(|&(x, y)| (y, x));
-        let lowest = self
-            .water
-            .keys()
-            .fold(0, |old, &(_, new)| std::cmp::max(old, new));
         Progress {
             bottom: self.walls.bottom,
-            lowest,
+            lowest: self.lowest,
             waters,
         }
     }
 
+    /// Flow until the water settles, calling `observer` with the step number and the progress
+    /// after every step. Returns the number of steps taken.
+    pub fn run<F: FnMut(u64, &Progress)>(&mut self, observer: F) -> u64 {
+        self.run_until(None, observer)
+    }
+
+    /// Like `run`, but stopping after `max_steps` steps, if given, even if the water hasn't
+    /// settled.
+    pub fn run_until<F>(&mut self, max_steps: Option<u64>, mut observer: F) -> u64
+    where
+        F: FnMut(u64, &Progress),
+    {
+        let mut steps = 0;
+        while Some(steps) != max_steps && self.step() {
+            steps += 1;
+            observer(steps, &self.progress());
+        }
+        steps
+    }
+
     fn find_bottom(&self, x: i64, y: i64) -> Option<(Edge, i64)> {
         for cy in y + 1..=self.walls.bottom {
             if self.water.get(&(x, cy)) == Some(&Water::Stable) {
@@ -232,6 +288,16 @@ impl FlowingWater {
         (left, right)
     }
 
+    // Queue a location to flow from, unless it's already waiting. Two streams landing in the
+    // same basin would otherwise queue its overflow twice.
+    fn push(&mut self, loc: (i64, i64)) {
+        if !self.queue.contains(&loc) {
+            self.queue.push_back(loc);
+        }
+    }
+
+    /// Flow from the next queued location. Returns false once the queue is empty and the
+    /// water has settled.
     fn step(&mut self) -> bool {
         let (x, y) = match self.queue.pop_front() {
             Some(v) => v,
@@ -248,7 +314,7 @@ impl FlowingWater {
                 //     self.walls.bottom,
                 // );
                 for cy in (y + 1..=self.walls.bottom).rev() {
-                    self.water.insert((x, cy), Water::Flowing);
+                    self.add_water((x, cy), Water::Flowing);
                 }
                 return true;
             }
@@ -256,7 +322,7 @@ impl FlowingWater {
         };
 
         if bottom_type == Edge::Water && bottom > y && self.seen.contains(&(x, y)) {
-            println!("Seen ({}, {})", x, y);
+            // Another stream already filled in below us
             return true;
         }
 
@@ -265,16 +331,16 @@ impl FlowingWater {
         // println!("Bottom found: ({}, {}) -> ({}, {})", x, y, x, bottom);
 
         for cy in (y + 1..=bottom).rev() {
-            self.water.insert((x, cy), Water::Flowing);
+            self.add_water((x, cy), Water::Flowing);
         }
 
         let sides = self.find_sides(x, bottom);
         if let ((Edge::Wall, lx), (Edge::Wall, rx)) = sides {
             // println!("       found double wall");
             for sx in (lx..=rx).rev() {
-                self.water.insert((sx, bottom), Water::Stable);
+                self.add_water((sx, bottom), Water::Stable);
             }
-            self.queue.push_back((x, bottom - 1));
+            self.push((x, bottom - 1));
             return true;
         }
 
@@ -284,14 +350,14 @@ impl FlowingWater {
         //     left_edge, lx, right_edge, rx
         // );
         for sx in lx..=rx {
-            self.water.insert((sx, bottom), Water::Flowing);
+            self.add_water((sx, bottom), Water::Flowing);
         }
 
         match left_edge {
             Edge::Wall => {}
             Edge::FreeFall => {
                 // println!("Pushing left edge ({}, {})", lx, bottom);
-                self.queue.push_back((lx, bottom));
+                self.push((lx, bottom));
             }
             Edge::Water => panic!("This shouldn't happen"),
         }
@@ -304,7 +370,7 @@ impl FlowingWater {
         match right_edge {
             Edge::Wall => {}
             Edge::FreeFall => {
-                self.queue.push_back((rx, bottom));
+                self.push((rx, bottom));
             }
             Edge::Water => panic!("This shouldn't happen"),
         }
@@ -332,33 +398,72 @@ impl FlowingWater {
         lines
     }
 
-    fn print(&self) {
+    /// Write the grid of walls (`#`), stable (`~`) and flowing (`|`) water, and sand (`.`).
+    pub fn write_to<W: Write>(&self, mut w: W) -> std::io::Result<()> {
         for l in self.to_bytes() {
-            let s = String::from_utf8(l).unwrap();
-            println!("{}", s);
+            w.write_all(&l)?;
+            w.write_all(b"\n")?;
         }
+        Ok(())
+    }
+
+    fn print(&self) {
+        let stdout = std::io::stdout();
+        self.write_to(stdout.lock()).unwrap();
+    }
+
+    /// Save the water, queue, and seen set as JSON, so the run can be resumed with load_state.
+    #[cfg(feature = "serde")]
+    pub fn save_state<W: Write>(&self, w: W) -> Result<(), failure::Error> {
+        let state = SavedState {
+            water: self.water.iter().map(|(&k, &v)| (k, v)).collect(),
+            queue: self.queue.iter().copied().collect(),
+            seen: self.seen.iter().copied().collect(),
+        };
+        serde_json::to_writer(w, &state)?;
+        Ok(())
+    }
+
+    /// Replace the water, queue, and seen set with those from save_state. The walls are kept,
+    /// so this should be called on a FlowingWater built from the same input.
+    #[cfg(feature = "serde")]
+    pub fn load_state<R: Read>(&mut self, r: R) -> Result<(), failure::Error> {
+        let state: SavedState = serde_json::from_reader(r)?;
+        self.water = state.water.into_iter().collect();
+        self.lowest = self.water.keys().fold(0, |old, &(_, y)| old.max(y));
+        self.queue = state.queue.into_iter().collect();
+        self.seen = state.seen.into_iter().collect();
+        Ok(())
     }
 
     /// water_count returns a count of (stable, flowing) water squares
     fn water_count(&self) -> (i64, i64) {
-        let (mut stable, mut flowing) = (0, 0);
+        self.water_by_row()
+            .values()
+            .fold((0, 0), |(s, f), &(rs, rf)| (s + rs, f + rf))
+    }
+
+    /// The (stable, flowing) water squares in each row with any water, by y.
+    fn water_by_row(&self) -> BTreeMap<i64, (i64, i64)> {
+        let mut rows = BTreeMap::new();
 
         for (&(_, y), water) in &self.water {
             if y < self.walls.top {
                 // These aren't counted
                 continue;
             }
+            let (stable, flowing) = rows.entry(y).or_insert((0, 0));
             match water {
-                Water::Flowing => flowing += 1,
-                Water::Stable => stable += 1,
+                Water::Flowing => *flowing += 1,
+                Water::Stable => *stable += 1,
             }
         }
 
-        (stable, flowing)
+        rows
     }
 }
 
-fn print_progress(step: i64, progress: Progress) {
+fn print_progress(step: u64, progress: &Progress) {
     println!(
         "-- Flowed {}: lowest {}, high flow {}, low flow {}, bottom {}, {} remaining",
         step,
@@ -370,6 +475,28 @@ fn print_progress(step: i64, progress: Progress) {
     );
 }
 
+#[cfg(feature = "serde")]
+fn load_state(flow: &mut FlowingWater, path: &str) -> Result<(), failure::Error> {
+    eprintln!("Resuming from {}", path);
+    flow.load_state(std::io::BufReader::new(std::fs::File::open(path)?))
+}
+
+#[cfg(not(feature = "serde"))]
+fn load_state(_: &mut FlowingWater, _: &str) -> Result<(), failure::Error> {
+    Err(failure::err_msg("--load-state requires the serde feature"))
+}
+
+#[cfg(feature = "serde")]
+fn save_state(flow: &FlowingWater, path: &str) -> Result<(), failure::Error> {
+    eprintln!("Saving state to {}", path);
+    flow.save_state(std::io::BufWriter::new(std::fs::File::create(path)?))
+}
+
+#[cfg(not(feature = "serde"))]
+fn save_state(_: &FlowingWater, _: &str) -> Result<(), failure::Error> {
+    Err(failure::err_msg("--save-state requires the serde feature"))
+}
+
 fn main() -> Result<(), failure::Error> {
     let matches = App::new("Day 17")
         .arg(
@@ -379,38 +506,84 @@ fn main() -> Result<(), failure::Error> {
                 .value_name("INPUT")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("max-steps")
+                .short("m")
+                .long("max-steps")
+                .value_name("STEPS")
+                .help("Stop flowing after this many steps, even if the water hasn't settled")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("spring-x")
+                .long("spring-x")
+                .value_name("X")
+                .help("x coordinate of the spring (default 500)")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("spring-y")
+                .long("spring-y")
+                .value_name("Y")
+                .help("y coordinate of the spring (default 0)")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("save-state")
+                .long("save-state")
+                .value_name("FILE")
+                .help(
+                    "Save the water state to this file when finished (requires the serde feature)",
+                )
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("load-state")
+                .long("load-state")
+                .value_name("FILE")
+                .help("Resume from a state saved with --save-state (requires the serde feature)")
+                .takes_value(true),
+        )
         .get_matches();
 
     let input_path = matches.value_of("INPUT").unwrap_or("inputs/day17.txt");
+    let max_steps: Option<u64> = match matches.value_of("max-steps") {
+        None => None,
+        Some(s) => Some(s.parse()?),
+    };
+    let spring_x: i64 = matches.value_of("spring-x").unwrap_or("500").parse()?;
+    let spring_y: i64 = matches.value_of("spring-y").unwrap_or("0").parse()?;
 
     eprintln!("Using input {}", input_path);
 
-    let file = File::open(input_path)?;
-    let buf_reader = BufReader::new(file);
-    let some_lines: std::io::Result<VecDeque<String>> = buf_reader.lines().collect();
-    let mut lines: VecDeque<String> = some_lines?;
-    let walls = Walls::parse_lines(&mut lines)?;
+    let walls = Walls::parse_lines(input::from_file(input_path)?)?;
 
-    let mut flow = FlowingWater::new(walls, (500, 0));
-    print_progress(0, flow.progress());
-    flow.print();
+    walls.check_spring((spring_x, spring_y))?;
 
-    let mut steps = 0;
-    while flow.step() {
-        steps += 1;
-        if steps % 100 == 0 {
-            print_progress(steps, flow.progress());
-            if steps % 10_000 == 0 {
-                flow.print();
-            }
-        }
+    let mut flow = FlowingWater::new(walls, (spring_x, spring_y));
+    if let Some(path) = matches.value_of("load-state") {
+        load_state(&mut flow, path)?;
+    }
+    print_progress(0, &flow.progress());
+    flow.print();
 
-        if steps > 100_000 {
-            break;
+    let steps = flow.run_until(max_steps, |step, progress| {
+        if step % 100 == 0 {
+            print_progress(step, progress);
         }
+    });
+    if !flow.queue.is_empty() {
+        eprintln!(
+            "Warning: stopped after {} steps with {} locations still queued; counts are incomplete",
+            steps,
+            flow.queue.len()
+        );
     }
 
     flow.print();
+    if let Some(path) = matches.value_of("save-state") {
+        save_state(&flow, path)?;
+    }
     let (s, f) = flow.water_count();
     println!("Finished after {} steps.", steps);
     println!("{} stable + {} flowing = {} water squares", s, f, s + f);
@@ -432,8 +605,7 @@ x=504, y=10..13
 y=13, x=498..504"#;
 
     fn get_test_walls(s: &str) -> Result<Walls, failure::Error> {
-        let lines: Vec<&str> = s.split('\n').collect();
-        Walls::parse_lines(lines)
+        Walls::parse_lines(input::from_str(s))
     }
 
     #[test]
@@ -453,6 +625,34 @@ y=13, x=498..504"#;
         flow.print();
     }
 
+    #[test]
+    fn test_parse_wall() {
+        let wall = Wall::parse_line("x=495, y=2..7").unwrap();
+        assert_eq!(
+            wall,
+            Wall {
+                direction: Direction::Horizontal,
+                loc: 495,
+                range: 2..=7,
+            }
+        );
+
+        let wall = Wall::parse_line("y=7, x=495..501").unwrap();
+        assert_eq!(
+            wall,
+            Wall {
+                direction: Direction::Vertical,
+                loc: 7,
+                range: 495..=501,
+            }
+        );
+
+        let err = Wall::parse_line("z=5, x=1..2").unwrap_err();
+        assert!(err.to_string().contains("z=5, x=1..2"), "{}", err);
+        assert!(Wall::parse_line("x=5, x=1..2").is_err());
+        assert!(Wall::parse_line("y=5, z=1..2").is_err());
+    }
+
     #[test]
     fn test_run() {
         println!("Getting test input...");
@@ -479,4 +679,161 @@ y=13, x=498..504"#;
         assert_eq!(28, f);
         assert_eq!(29, s);
     }
+
+    #[test]
+    fn test_water_by_row() {
+        let mut flow = FlowingWater::new(get_test_walls(TEST_INPUT).unwrap(), (500, 0));
+        flow.run(|_, _| {});
+
+        let rows = flow.water_by_row();
+        // The spring's row is above the top wall, so isn't included
+        assert_eq!(
+            rows.keys().cloned().collect::<Vec<_>>(),
+            (1..=13).collect::<Vec<_>>()
+        );
+        assert_eq!(rows[&1], (0, 1));
+        assert_eq!(rows[&2], (0, 4));
+        assert_eq!(rows[&5], (5, 1));
+        assert_eq!(rows[&9], (0, 9));
+        assert_eq!(rows[&10], (5, 2));
+        assert_eq!(rows[&13], (0, 2));
+
+        let stable: i64 = rows.values().map(|&(s, _)| s).sum();
+        let flowing: i64 = rows.values().map(|&(_, f)| f).sum();
+        assert_eq!((stable, flowing), (29, 28));
+        assert_eq!((stable, flowing), flow.water_count());
+    }
+
+    #[test]
+    fn test_run_observer() {
+        let mut manual = FlowingWater::new(get_test_walls(TEST_INPUT).unwrap(), (500, 0));
+        let mut manual_steps = 0;
+        while manual.step() {
+            manual_steps += 1;
+        }
+
+        let mut flow = FlowingWater::new(get_test_walls(TEST_INPUT).unwrap(), (500, 0));
+        let mut seen = Vec::new();
+        let steps = flow.run(|step, progress| seen.push((step, progress.waters.len())));
+
+        assert_eq!(steps, manual_steps);
+        let ticks: Vec<u64> = seen.iter().map(|&(step, _)| step).collect();
+        assert_eq!(ticks, (1..=manual_steps).collect::<Vec<u64>>());
+        // Nothing is left queued after the last step
+        assert_eq!(seen.last().map(|&(_, queued)| queued), Some(0));
+        assert_eq!(flow.water_count(), manual.water_count());
+        assert_eq!(flow.water_count(), (29, 28));
+
+        // Stopping a step early leaves work queued
+        let mut flow = FlowingWater::new(get_test_walls(TEST_INPUT).unwrap(), (500, 0));
+        let mut count = 0;
+        let stop = manual_steps - 1;
+        assert_eq!(flow.run_until(Some(stop), |_, _| count += 1), stop);
+        assert_eq!(count, stop);
+        assert!(!flow.queue.is_empty());
+    }
+
+    #[test]
+    fn test_terminates() {
+        // Ten basins stacked below the spring, each wider than the last, so each one overflows
+        // on both sides into the next
+        let mut lines = Vec::new();
+        for k in 0..10 {
+            let (w, y) = (2 + 2 * k, 1 + 5 * k);
+            lines.push(format!("x={}, y={}..{}", 500 - w, y, y + 3));
+            lines.push(format!("x={}, y={}..{}", 500 + w, y, y + 3));
+            lines.push(format!("y={}, x={}..{}", y + 3, 500 - w, 500 + w));
+        }
+        let walls = Walls::parse_lines(lines).unwrap();
+        let mut flow = FlowingWater::new(walls, (500, 0));
+
+        let mut steps = 0;
+        while flow.step() {
+            steps += 1;
+            assert!(steps < 1000, "Didn't settle in {} steps", steps);
+        }
+        assert!(flow.queue.is_empty());
+
+        // Each basin is 3 deep, and 2w - 1 wide on the inside
+        let (s, _) = flow.water_count();
+        let expected: i64 = (0..10).map(|k| 3 * (2 * (2 + 2 * k) - 1)).sum();
+        assert_eq!(s, expected);
+    }
+
+    #[test]
+    fn test_write_to() {
+        let walls = get_test_walls(TEST_INPUT).unwrap();
+        let mut flow = FlowingWater::new(walls, (500, 0));
+        while flow.step() {}
+
+        let mut out = Vec::new();
+        flow.write_to(&mut out).unwrap();
+        let grid = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = grid.lines().collect();
+        assert_eq!(lines.len(), 14);
+        assert_eq!(lines[6], ".#~~~~~#|.....");
+        assert_eq!(lines[13], "...|#######|..");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_save_load() {
+        let walls = get_test_walls(TEST_INPUT).unwrap();
+        let mut flow = FlowingWater::new(walls.clone(), (500, 0));
+        for _ in 0..3 {
+            assert!(flow.step());
+        }
+
+        let mut saved = Vec::new();
+        flow.save_state(&mut saved).unwrap();
+        let mut loaded = FlowingWater::new(walls, (500, 0));
+        loaded.load_state(&saved[..]).unwrap();
+        assert_eq!(loaded.water_count(), flow.water_count());
+        assert_eq!(loaded.queue, flow.queue);
+
+        while flow.step() {}
+        while loaded.step() {}
+        assert_eq!(loaded.water_count(), flow.water_count());
+        assert_eq!(loaded.water_count(), (29, 28));
+    }
+
+    #[test]
+    fn test_shifted_spring() {
+        // TEST_INPUT moved 200 to the left and 3 down
+        let shifted = r#"
+x=295, y=5..10
+y=10, x=295..301
+x=301, y=6..10
+x=298, y=5..7
+x=306, y=4..5
+x=298, y=13..16
+x=304, y=13..16
+y=16, x=298..304"#;
+        let walls = get_test_walls(shifted).unwrap();
+        assert!(walls.check_spring((500, 0)).is_err());
+        assert!(walls.check_spring((300, 0)).is_ok());
+
+        let mut flow = FlowingWater::new(walls, (300, 0));
+        while flow.step() {}
+        // The spring's column above y=4 isn't counted, so this matches the unshifted input
+        assert_eq!(flow.water_count(), (29, 28));
+    }
+
+    #[test]
+    fn test_spring_below_top() {
+        // The spring's own square counts once it's at or below the top wall
+        let walls = Walls::parse_lines(vec!["x=100, y=20..25"]).unwrap();
+
+        let mut flow = FlowingWater::new(walls.clone(), (3, 20));
+        while flow.step() {}
+        assert_eq!(flow.water_count(), (0, 6));
+
+        let mut flow = FlowingWater::new(walls.clone(), (3, 22));
+        while flow.step() {}
+        assert_eq!(flow.water_count(), (0, 4));
+
+        let mut flow = FlowingWater::new(walls, (3, 19));
+        while flow.step() {}
+        assert_eq!(flow.water_count(), (0, 6));
+    }
 }
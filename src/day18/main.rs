@@ -1,11 +1,8 @@
+use aoc::input;
 use clap::{App, Arg};
 
-use std::cmp::{max, min};
 use std::collections::hash_map::Entry;
-use std::collections::{HashMap, VecDeque};
-use std::fs::File;
-use std::io::prelude::*;
-use std::io::BufReader;
+use std::collections::HashMap;
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 enum Acre {
@@ -17,6 +14,8 @@ enum Acre {
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Area {
     acres: Vec<Vec<Acre>>,
+    // Whether the edges wrap around, so the grid is a torus
+    wrap: bool,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
@@ -27,6 +26,30 @@ pub struct State {
     pub lumberyards: usize,
 }
 
+/// The thresholds for each transition in Area::advance. The default is the puzzle's rules.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Rules {
+    /// Open acres with at least this many adjacent trees fill with trees
+    pub open_to_trees: usize,
+    /// Trees with at least this many adjacent lumberyards become a lumberyard
+    pub trees_to_lumberyard: usize,
+    /// Lumberyards stay lumberyards with at least this many adjacent lumberyards...
+    pub lumberyard_keeps_lumberyards: usize,
+    /// ...and at least this many adjacent trees; otherwise they become open
+    pub lumberyard_keeps_trees: usize,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Rules {
+            open_to_trees: 3,
+            trees_to_lumberyard: 3,
+            lumberyard_keeps_lumberyards: 1,
+            lumberyard_keeps_trees: 1,
+        }
+    }
+}
+
 impl Area {
     fn parse_line<S>(line: S) -> Result<Vec<Acre>, failure::Error>
     where
@@ -45,6 +68,7 @@ impl Area {
             .collect()
     }
 
+    // Parse an area from lines, as given by aoc::input: trimmed, with no blank lines.
     pub fn parse_lines<I, S>(lines: I) -> Result<Self, failure::Error>
     where
         S: AsRef<str>,
@@ -52,17 +76,18 @@ impl Area {
     {
         let result: Result<Vec<Vec<Acre>>, failure::Error> = lines
             .into_iter()
-            .filter_map(|l| {
-                let trimmed = l.as_ref().trim();
-                if trimmed.is_empty() {
-                    None
-                } else {
-                    Some(Area::parse_line(trimmed))
-                }
-            })
+            .map(|l| Area::parse_line(l.as_ref()))
             .collect();
 
-        Ok(Area { acres: result? })
+        Ok(Area {
+            acres: result?,
+            wrap: false,
+        })
+    }
+
+    /// Set whether neighborhoods wrap around the edges of the grid, rather than stopping there.
+    pub fn set_wrap(&mut self, wrap: bool) {
+        self.wrap = wrap;
     }
 
     pub fn state(&self) -> State {
@@ -103,35 +128,54 @@ impl Area {
         if self.acres.is_empty() {
             return (0, 0);
         }
+        if self.wrap {
+            return self.get_wrapped_neighbors(row, col);
+        }
+        let height = self.acres.len();
         let width = self.acres[0].len();
         let (mut trees, mut lumberyards) = (0, 0);
-        let row_start_ix = max(row, 1) - 1;
-        let row_end_ix = min(row + 2, self.acres.len());
-        let col_start_ix = max(col, 1) - 1;
-        let col_end_ix = min(col + 2, width);
-
-        // println!("Neighbors ({}, {}):", row, col);
 
-        for (rix, acres) in self
-            .acres
-            .iter()
-            .enumerate()
-            .skip(row_start_ix)
-            .take(row_end_ix - row_start_ix)
-        {
-            for (cix, acre) in acres
-                .iter()
-                .enumerate()
-                .skip(col_start_ix)
-                .take(col_end_ix - col_start_ix)
-            {
-                if (rix == row) && (cix == col) {
+        // Scan the (at most) 3x3 block around the cell directly, clipped to the grid. This is
+        // called for every cell on every generation, so it's worth avoiding building and
+        // bounds-checking the full list of 8 neighbors, which should speed up advance.
+        let (top, bottom) = (row.saturating_sub(1), (row + 1).min(height - 1));
+        let (left, right) = (col.saturating_sub(1), (col + 1).min(width - 1));
+        for (r, acres) in self.acres[top..=bottom].iter().enumerate() {
+            for (c, acre) in acres[left..=right].iter().enumerate() {
+                if top + r == row && left + c == col {
                     continue;
                 }
+                match acre {
+                    Acre::Open => {}
+                    Acre::Trees => trees += 1,
+                    Acre::Lumberyard => lumberyards += 1,
+                }
+            }
+        }
+
+        (trees, lumberyards)
+    }
 
-                // println!("          ({}, {}): {:?}", rix, cix, acre);
+    fn get_wrapped_neighbors(&self, row: usize, col: usize) -> (usize, usize) {
+        let height = self.acres.len();
+        let width = self.acres[0].len();
+        let (mut trees, mut lumberyards) = (0, 0);
 
-                match acre {
+        // The rows and columns one step away on either side, wrapped. On grids less than 3
+        // across, some of those are the same square, which should only be counted once.
+        let mut rows = vec![(row + height - 1) % height, row, (row + 1) % height];
+        rows.sort_unstable();
+        rows.dedup();
+        let mut cols = vec![(col + width - 1) % width, col, (col + 1) % width];
+        cols.sort_unstable();
+        cols.dedup();
+
+        for &r in &rows {
+            for &c in &cols {
+                if r == row && c == col {
+                    continue;
+                }
+                match self.acres[r][c] {
                     Acre::Open => {}
                     Acre::Trees => trees += 1,
                     Acre::Lumberyard => lumberyards += 1,
@@ -142,7 +186,7 @@ impl Area {
         (trees, lumberyards)
     }
 
-    pub fn advance(&mut self) -> bool {
+    pub fn advance(&mut self, rules: &Rules) -> bool {
         let height = self.acres.len();
         if height == 0 {
             return false;
@@ -158,11 +202,16 @@ impl Area {
                 let (trees, lumberyards) = self.get_neighbors(rix, cix);
 
                 let new_acre = match acre {
-                    Acre::Open if trees >= 3 => Acre::Trees,
+                    Acre::Open if trees >= rules.open_to_trees => Acre::Trees,
                     Acre::Open => Acre::Open,
-                    Acre::Trees if lumberyards >= 3 => Acre::Lumberyard,
+                    Acre::Trees if lumberyards >= rules.trees_to_lumberyard => Acre::Lumberyard,
                     Acre::Trees => Acre::Trees,
-                    Acre::Lumberyard if lumberyards >= 1 && trees >= 1 => Acre::Lumberyard,
+                    Acre::Lumberyard
+                        if lumberyards >= rules.lumberyard_keeps_lumberyards
+                            && trees >= rules.lumberyard_keeps_trees =>
+                    {
+                        Acre::Lumberyard
+                    }
                     Acre::Lumberyard => Acre::Open,
                 };
 
@@ -187,35 +236,71 @@ impl Area {
 pub struct Tracker {
     time: usize,
     area: Area,
+    rules: Rules,
     seen: HashMap<Area, usize>,
     history: Vec<Area>,
     repeats: Option<(usize, Vec<Area>)>,
 }
 
+// The element for time t, given one per time step before a cycle starts, and one per time step
+// of the cycle (which starts right after the prefix). None if t is past a prefix with no cycle.
+fn project<'a, T>(prefix: &'a [T], cycle: &'a [T], t: usize) -> Option<&'a T> {
+    if t < prefix.len() {
+        return prefix.get(t);
+    }
+    if cycle.is_empty() {
+        return None;
+    }
+    Some(&cycle[(t - prefix.len()) % cycle.len()])
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+fn value(area: &Area) -> usize {
+    let state = area.state();
+    state.trees * state.lumberyards
+}
+
 impl Tracker {
     pub fn new(area: Area) -> Self {
+        Tracker::with_rules(area, Rules::default())
+    }
+
+    pub fn with_rules(area: Area, rules: Rules) -> Self {
         Tracker {
+            history: vec![area.clone()],
             area,
+            rules,
             time: Default::default(),
             seen: Default::default(),
-            history: Default::default(),
             repeats: None,
         }
     }
 
-    fn advance(&mut self) {
-        if self.time == 0 {
-            self.history.push(self.area.clone());
+    // The cycle found so far, if any
+    fn cycle(&self) -> &[Area] {
+        match &self.repeats {
+            Some((_, reps)) => reps,
+            None => &[],
         }
+    }
 
+    fn advance(&mut self) {
         self.time += 1;
-        if let Some((start, reps)) = &self.repeats {
-            let ix = (self.time - start) % reps.len();
-            self.area = reps[ix].clone();
+        if self.repeats.is_some() {
+            self.area = project(&self.history, self.cycle(), self.time)
+                .unwrap()
+                .clone();
             return;
         }
 
-        self.area.advance();
+        self.area.advance(&self.rules);
         let cloned = self.area.clone();
         let repeat_time = match self.seen.entry(cloned) {
             Entry::Vacant(v) => {
@@ -232,8 +317,8 @@ impl Tracker {
             self.history.len(),
             repeat_time
         );
+        // What's left in history is everything before the cycle
         let reps = self.history.split_off(repeat_time);
-        self.history.clear();
         self.seen.clear();
         println!(
             "Found repeat, {} -> {} ({})",
@@ -244,6 +329,34 @@ impl Tracker {
         self.repeats = Some((repeat_time, reps));
     }
 
+    // The number of time steps whose area is known, or None if every one is (from the cycle)
+    fn known_times(&self) -> Option<usize> {
+        match self.repeats {
+            Some(_) => None,
+            None => Some(self.history.len()),
+        }
+    }
+
+    /// The first time at which the two trackers' areas differ, or None if they agree as far as
+    /// both have been simulated. If both have found their cycles, they're compared for all time:
+    /// once both are in their cycles, they repeat together after the least common multiple of the
+    /// cycle lengths.
+    pub fn first_divergence(&self, other: &Tracker) -> Option<usize> {
+        let end = match (self.known_times(), other.known_times()) {
+            (Some(n), Some(m)) => n.min(m),
+            (Some(n), None) | (None, Some(n)) => n,
+            (None, None) => {
+                let (a, b) = (self.cycle().len(), other.cycle().len());
+                let start = self.history.len().max(other.history.len());
+                start + a / gcd(a, b) * b
+            }
+        };
+
+        (0..end).find(|&t| {
+            project(&self.history, self.cycle(), t) != project(&other.history, other.cycle(), t)
+        })
+    }
+
     pub fn advance_to(&mut self, t: usize) {
         while self.repeats.is_none() {
             if t <= self.time {
@@ -253,13 +366,22 @@ impl Tracker {
             self.advance();
         }
 
-        if let Some((start, reps)) = &self.repeats {
-            let ix = (t - start) % reps.len();
-            self.area = reps[ix].clone();
-            self.time = t;
-        } else {
-            unreachable!()
+        self.area = project(&self.history, self.cycle(), t).unwrap().clone();
+        self.time = t;
+    }
+
+    /// The resource value (trees * lumberyards) at each time from 0 through t. Once the cycle
+    /// is found, later values are read off of it rather than simulated.
+    pub fn values_through(&mut self, t: usize) -> Vec<usize> {
+        while self.repeats.is_none() && self.time < t {
+            self.advance();
         }
+
+        let prefix: Vec<usize> = self.history.iter().map(value).collect();
+        let cycle: Vec<usize> = self.cycle().iter().map(value).collect();
+        (0..=t)
+            .map(|i| *project(&prefix, &cycle, i).unwrap())
+            .collect()
     }
 }
 
@@ -278,11 +400,7 @@ fn main() -> Result<(), failure::Error> {
 
     eprintln!("Using input {}", input_path);
 
-    let file = File::open(input_path)?;
-    let buf_reader = BufReader::new(file);
-    let some_lines: std::io::Result<VecDeque<String>> = buf_reader.lines().collect();
-    let mut lines: VecDeque<String> = some_lines?;
-    let area = Area::parse_lines(&mut lines)?;
+    let area = Area::parse_lines(input::from_file(input_path)?)?;
 
     let mut tracker = Tracker::new(area);
     tracker.advance_to(10);
@@ -461,8 +579,7 @@ mod tests {
     ];
 
     fn get_test_area(s: &str) -> Result<Area, failure::Error> {
-        let lines: Vec<&str> = s.split('\n').collect();
-        Area::parse_lines(lines)
+        Area::parse_lines(input::from_str(s))
     }
 
     #[test]
@@ -497,13 +614,72 @@ mod tests {
         assert_eq!(nbr, (2, 2));
     }
 
+    #[test]
+    fn test_neighbors_match_listed() {
+        // The direct scan should agree with checking each of the 8 listed neighbors
+        let rules = Rules::default();
+        let mut area = get_test_area(TEST_INPUTS[0]).unwrap();
+        for _ in 0..10 {
+            for (r, row) in area.acres.iter().enumerate() {
+                for c in 0..row.len() {
+                    let (mut trees, mut lumberyards) = (0, 0);
+                    for &(nr, nc) in &aoc::grid::neighbors8((r as i64, c as i64)) {
+                        if nr < 0 || nc < 0 {
+                            continue;
+                        }
+                        match area.acres.get(nr as usize).and_then(|a| a.get(nc as usize)) {
+                            None | Some(Acre::Open) => {}
+                            Some(Acre::Trees) => trees += 1,
+                            Some(Acre::Lumberyard) => lumberyards += 1,
+                        }
+                    }
+                    assert_eq!(area.get_neighbors(r, c), (trees, lumberyards));
+                }
+            }
+            area.advance(&rules);
+        }
+    }
+
+    #[test]
+    fn test_wrapped_neighbors() {
+        let mut area = get_test_area(
+            r#"
+            ..#
+            ...
+            |.|
+        "#,
+        )
+        .unwrap();
+
+        // The top-left corner only sees its opposite corners when wrapping
+        assert_eq!(area.get_neighbors(0, 0), (0, 0));
+        area.set_wrap(true);
+        assert_eq!(area.get_neighbors(0, 0), (2, 1));
+        assert_eq!(area.get_neighbors(1, 1), (2, 1));
+        assert_eq!(area.get_neighbors(2, 2), (1, 1));
+
+        // On a 2-wide grid, left and right wrap to the same column
+        let mut area = get_test_area(
+            r#"
+            |#
+            ..
+            #|
+        "#,
+        )
+        .unwrap();
+        area.set_wrap(true);
+        assert_eq!(area.get_neighbors(0, 0), (1, 2));
+        assert_eq!(area.get_neighbors(1, 0), (2, 2));
+        assert_eq!(area.get_neighbors(1, 1), (2, 2));
+    }
+
     #[test]
     fn test_advance() {
         let mut area = get_test_area(TEST_INPUTS[0]).unwrap();
 
         let mut min = 0;
         for input in TEST_INPUTS.iter().skip(1) {
-            area.advance();
+            area.advance(&Rules::default());
             min += 1;
             let stepped = get_test_area(input).unwrap();
             assert_eq!(area.acres, stepped.acres);
@@ -520,15 +696,53 @@ mod tests {
         assert_eq!(area.state(), expected_state);
     }
 
+    #[test]
+    fn test_rules() {
+        let canonical = get_test_area(TEST_INPUTS[1]).unwrap();
+
+        let mut area = get_test_area(TEST_INPUTS[0]).unwrap();
+        area.advance(&Rules::default());
+        assert_eq!(area, canonical);
+
+        let rules = Rules {
+            open_to_trees: 2,
+            ..Rules::default()
+        };
+        let mut area = get_test_area(TEST_INPUTS[0]).unwrap();
+        area.advance(&rules);
+        assert_ne!(area, canonical);
+        assert!(area.state().trees > canonical.state().trees);
+    }
+
+    #[test]
+    fn test_values_through() {
+        let mut tracker = Tracker::new(get_test_area(TEST_INPUTS[0]).unwrap());
+        tracker.advance_to(1000);
+        let (start, reps) = tracker.repeats.as_ref().unwrap();
+        let t = start + 2 * reps.len();
+
+        let mut area = get_test_area(TEST_INPUTS[0]).unwrap();
+        let mut expected = vec![value(&area)];
+        for _ in 0..t {
+            area.advance(&Rules::default());
+            expected.push(value(&area));
+        }
+
+        let mut tracker = Tracker::new(get_test_area(TEST_INPUTS[0]).unwrap());
+        assert_eq!(tracker.values_through(t), expected);
+        assert_eq!(tracker.values_through(10)[10], 37 * 31);
+    }
+
     #[test]
     fn test_tracker() {
         let area = get_test_area(TEST_INPUTS[0]).unwrap();
 
         let mut tracker = Tracker::new(area);
         let mut area = get_test_area(TEST_INPUTS[0]).unwrap();
+        let rules = Rules::default();
 
         while tracker.repeats.is_none() {
-            area.advance();
+            area.advance(&rules);
             tracker.advance();
         }
 
@@ -537,7 +751,7 @@ mod tests {
         println!("Repeats with loop {} after {}", reps.len(), start);
 
         for _ in 0..=reps.len() * 2 {
-            area.advance();
+            area.advance(&rules);
             tracker.advance();
 
             assert_eq!(area, tracker.area);
@@ -549,4 +763,32 @@ mod tests {
             assert_eq!(area, new_tracker.area);
         }
     }
+
+    #[test]
+    fn test_first_divergence() {
+        let mut tracker = Tracker::new(get_test_area(TEST_INPUTS[0]).unwrap());
+        let rules = Rules {
+            lumberyard_keeps_trees: 0,
+            ..Rules::default()
+        };
+        let mut other = Tracker::with_rules(get_test_area(TEST_INPUTS[0]).unwrap(), rules);
+
+        // Nothing simulated yet, so they only have the (same) start to compare
+        assert_eq!(tracker.first_divergence(&other), None);
+
+        tracker.advance_to(1);
+        other.advance_to(1);
+        assert_eq!(tracker.first_divergence(&other), None);
+
+        tracker.advance_to(1000);
+        other.advance_to(1000);
+        assert_eq!(tracker.first_divergence(&other), Some(2));
+        assert_eq!(other.first_divergence(&tracker), Some(2));
+
+        // Trackers that stay the same agree for all time, including across their cycles
+        let mut same = Tracker::new(get_test_area(TEST_INPUTS[0]).unwrap());
+        same.advance_to(1000);
+        assert!(same.repeats.is_some());
+        assert_eq!(tracker.first_divergence(&same), None);
+    }
 }
@@ -1,6 +1,6 @@
 #![warn(clippy::all)]
 
-use aoc::device::{parse_instructions, Device};
+use aoc::device::{parse_instructions, Device, Instruction, OpCode, Value};
 
 use clap::{App, Arg};
 
@@ -9,6 +9,142 @@ use std::fs::File;
 use std::io::prelude::*;
 use std::io::BufReader;
 
+/// The loop these programs spend nearly all their time in: for each i and j in 1..=n, add i to
+/// an accumulator if i * j == n. In other words, it sums the divisors of n, very slowly.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct DivisorSum {
+    // The instruction the loop starts at; it ends 15 instructions later
+    start: usize,
+    // Registers
+    n: usize,
+    acc: usize,
+    i: usize,
+    j: usize,
+    tmp: usize,
+}
+
+impl DivisorSum {
+    const LEN: usize = 15;
+
+    fn find(bound: usize, instructions: &[Instruction]) -> Option<Self> {
+        instructions
+            .windows(DivisorSum::LEN)
+            .enumerate()
+            .find_map(|(start, window)| DivisorSum::matching(bound, start, window))
+    }
+
+    // Match one window of instructions against the loop, with any choice of registers.
+    fn matching(b: usize, start: usize, w: &[Instruction]) -> Option<Self> {
+        use OpCode::*;
+
+        let (i, j) = match (w[0], w[1]) {
+            (Instruction(SetI, 1, _, i), Instruction(SetI, 1, _, j)) => (i, j),
+            _ => return None,
+        };
+        let tmp = match w[2] {
+            Instruction(MulR, x, y, t) if (x, y) == (i, j) || (x, y) == (j, i) => t,
+            _ => return None,
+        };
+        let n = match w[3] {
+            Instruction(EqRR, x, n, t) if x == tmp && t == tmp => n,
+            Instruction(EqRR, n, y, t) if y == tmp && t == tmp => n,
+            _ => return None,
+        };
+        let acc = match w[6] {
+            Instruction(AddR, x, a, c) if x == i && a == c => a,
+            Instruction(AddR, a, y, c) if y == i && a == c => a,
+            _ => return None,
+        };
+
+        let expected = [
+            (4, Instruction(AddR, tmp, b, b)),
+            (5, Instruction(AddI, b, 1, b)),
+            (7, Instruction(AddI, j, 1, j)),
+            (8, Instruction(GtRR, j, n, tmp)),
+            (9, Instruction(AddR, b, tmp, b)),
+            (11, Instruction(AddI, i, 1, i)),
+            (12, Instruction(GtRR, i, n, tmp)),
+            (13, Instruction(AddR, tmp, b, b)),
+        ];
+        if expected.iter().any(|&(ix, instr)| w[ix] != instr) {
+            return None;
+        }
+
+        // The jumps back to the start of each loop
+        match (w[10], w[14]) {
+            (Instruction(SetI, inner, _, c1), Instruction(SetI, outer, _, c2))
+                if inner == start + 1 && outer == start && c1 == b && c2 == b => {}
+            _ => return None,
+        }
+
+        Some(DivisorSum {
+            start,
+            n,
+            acc,
+            i,
+            j,
+            tmp,
+        })
+    }
+
+    /// Leave the device just as running the loop would, without running it.
+    fn shortcut(&self, device: &mut Device) {
+        let values = &mut device.register.values;
+        let n = values[self.n];
+        values[self.acc] += divisor_sum(n);
+        // Both loops run their counter until it's past n, and each runs at least once
+        let end = std::cmp::max(n, 1) + 1;
+        values[self.i] = end;
+        values[self.j] = end;
+        values[self.tmp] = 1;
+        device.pointer = self.start + DivisorSum::LEN;
+    }
+}
+
+fn divisor_sum(n: Value) -> Value {
+    let mut sum = 0;
+    let mut i = 1;
+    while i * i <= n {
+        if n % i == 0 {
+            sum += i;
+            if i * i != n {
+                sum += n / i;
+            }
+        }
+        i += 1;
+    }
+    sum
+}
+
+/// Run the device until it halts, returning the number of instructions executed. Only used to
+/// check run_optimized against.
+#[cfg(test)]
+fn run(device: &mut Device) -> usize {
+    let mut steps = 0;
+    while device.apply() {
+        steps += 1;
+    }
+    steps
+}
+
+/// Run the device until it halts, computing any divisor-sum loop directly instead of running
+/// it. Returns the number of instructions executed.
+fn run_optimized(device: &mut Device) -> usize {
+    let loop_found = DivisorSum::find(device.bound, &device.instructions);
+
+    let mut steps = 0;
+    loop {
+        if let Some(l) = loop_found.filter(|l| l.start == device.pointer) {
+            l.shortcut(device);
+            continue;
+        }
+        if !device.apply() {
+            return steps;
+        }
+        steps += 1;
+    }
+}
+
 fn main() -> Result<(), failure::Error> {
     let matches = App::new("Day 19")
         .arg(
@@ -34,61 +170,17 @@ fn main() -> Result<(), failure::Error> {
         pointer,
         instructions.len()
     );
-    let mut d = Device::new(6, pointer, instructions.clone());
-
-    let mut steps = 0;
-    while d.apply() {
-        steps += 1;
-        // println!("{} Pointer {}, {:?}", steps, d.pointer, d.register.values);
-    }
-
-    println!("Finished after {} steps: {:?}", steps, d.register.values);
-
-    let n = 10_551_311;
-    println!("Factors of {}: {:?}", n, primes::factors(n));
-
-    let mut d2 = Device::new(6, pointer, instructions.clone());
-
-    // d2.register.values[0] = 1;
-
-    // d2.register.values = vec![0, 10_551_311, 1, 0, 7, 10_551_310];
-    // d2.pointer = 8;
-
-    // d2.register.values = vec![1, 10_551_311, 2, 0, 7, 10_551_309];
-    // d2.pointer = 8;
-
-    // d2.register.values = vec![1, 10_551_311, 431, 0, 7, 24480];
-    // d2.pointer = 8;
-
-    // d2.register.values = vec![432, 10_551_311, 24481, 0, 7, 430];
-    // d2.pointer = 8;
-
-    // d2.register.values = vec![24913, 10_551_311, 10_551_310, 0, 7, 10_551_310];
-    // d2.pointer = 8;
-
-    // It sums the factors - in this case, 1 + 431 + 24481 + 10551311
-    d2.register.values = vec![10_576_224, 10_551_311, 10_551_311, 0, 7, 10_551_310];
-    d2.pointer = 8;
 
-    let mut steps = 0;
-
-    while d2.apply() {
-        steps += 1;
+    for (part, start) in [(1, 0), (2, 1)].iter() {
+        let mut d = Device::new(6, pointer, instructions.clone());
+        d.register.values[0] = *start;
+        let steps = run_optimized(&mut d);
         println!(
-            "{} Pointer {} ({:?}), {:?}",
-            steps,
-            d2.pointer,
-            instructions.get(d2.pointer),
-            d2.register.values
+            "Part {}: finished after {} steps with register[0] = {}",
+            part, steps, d.register.values[0]
         );
-
-        if steps > 100 {
-            break;
-        }
     }
 
-    println!("Finished after {} steps: {:?}", steps, d2.register.values);
-
     Ok(())
 }
 
@@ -139,4 +231,57 @@ mod tests {
         assert_eq!(dev.register.values, vec![6, 5, 6, 0, 0, 9]);
         assert_eq!(dev.pointer, 7);
     }
+
+    // The same shape as the puzzle input, but only summing the divisors of 12 (or 24, if
+    // register 0 starts at 1)
+    const DIVISOR_INPUT: &str = r#"
+        #ip 4
+        addi 4 16 4
+        seti 1 7 2
+        seti 1 1 5
+        mulr 2 5 3
+        eqrr 3 1 3
+        addr 3 4 4
+        addi 4 1 4
+        addr 2 0 0
+        addi 5 1 5
+        gtrr 5 1 3
+        addr 4 3 4
+        seti 2 7 4
+        addi 2 1 2
+        gtrr 2 1 3
+        addr 3 4 4
+        seti 1 3 4
+        mulr 4 4 4
+        seti 12 0 1
+        mulr 0 1 3
+        addr 1 3 1
+        seti 0 0 0
+        seti 0 0 4
+    "#;
+
+    #[test]
+    fn test_optimized() {
+        let lines: Vec<&str> = DIVISOR_INPUT.split('\n').collect();
+        let (pointer, instructions) = parse_instructions(lines).unwrap();
+        assert_eq!(
+            DivisorSum::find(pointer, &instructions).map(|l| l.start),
+            Some(1)
+        );
+
+        for &(start, expected) in &[(0, 1 + 2 + 3 + 4 + 6 + 12), (1, 60)] {
+            let mut brute = Device::new(6, pointer, instructions.clone());
+            brute.register.values[0] = start;
+            let brute_steps = run(&mut brute);
+
+            let mut optimized = Device::new(6, pointer, instructions.clone());
+            optimized.register.values[0] = start;
+            let optimized_steps = run_optimized(&mut optimized);
+
+            assert_eq!(brute.register.values, optimized.register.values);
+            assert_eq!(brute.pointer, optimized.pointer);
+            assert_eq!(optimized.register.values[0], expected);
+            assert!(optimized_steps < brute_steps);
+        }
+    }
 }
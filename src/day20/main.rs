@@ -2,7 +2,7 @@ use clap::{App, Arg};
 
 use std::cmp::Ordering;
 use std::collections::hash_map::Entry;
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::fs::File;
 use std::io::prelude::*;
 use std::io::BufReader;
@@ -34,20 +34,26 @@ impl FromStr for Building {
         let mut distances: HashMap<Room, i64> = HashMap::new();
         let mut connections: HashMap<Room, HashSet<Room>> = HashMap::new();
 
-        let mut parents: Vec<Room> = Vec::new();
+        // Each open group's starting room, and the offset of its '('
+        let mut parents: Vec<(Room, usize)> = Vec::new();
 
         let mut room: Room = Default::default();
         distances.insert(room, 0);
         connections.insert(room, Default::default());
 
-        for (_ix, b) in s.bytes().enumerate() {
-            // let read_so_far = || std::str::from_utf8(&s.as_bytes()[..=ix]).unwrap();
+        let bytes = s.as_bytes();
+        if bytes.first() != Some(&b'^') {
+            return Err(failure::err_msg("Expected '^' at offset 0"));
+        }
+        if bytes.len() < 2 || bytes.last() != Some(&b'$') {
+            return Err(failure::format_err!(
+                "Expected '$' at offset {}",
+                bytes.len().saturating_sub(1)
+            ));
+        }
+
+        for (ix, &b) in bytes.iter().enumerate().take(bytes.len() - 1).skip(1) {
             let last = match b {
-                b'^' => {
-                    assert_eq!(distances.len(), 1);
-                    continue;
-                }
-                b'$' => break,
                 b'N' => {
                     let last = room;
                     room.y += 1;
@@ -69,18 +75,35 @@ impl FromStr for Building {
                     last
                 }
                 b'(' => {
-                    parents.push(room);
+                    parents.push((room, ix));
                     continue;
                 }
                 b'|' => {
-                    room = *parents.last().unwrap();
+                    room = match parents.last() {
+                        Some(&(r, _)) => r,
+                        None => {
+                            return Err(failure::format_err!(
+                                "'|' outside of any group at offset {}",
+                                ix
+                            ))
+                        }
+                    };
                     continue;
                 }
                 b')' => {
-                    room = parents.pop().unwrap();
+                    room = match parents.pop() {
+                        Some((r, _)) => r,
+                        None => return Err(failure::format_err!("Unmatched ')' at offset {}", ix)),
+                    };
                     continue;
                 }
-                _ => panic!("unrecognized character: {}", b),
+                _ => {
+                    return Err(failure::format_err!(
+                        "Unrecognized character {:?} at offset {}",
+                        b as char,
+                        ix
+                    ))
+                }
             };
 
             connections.entry(last).or_default().insert(room);
@@ -140,6 +163,10 @@ impl FromStr for Building {
             }
         }
 
+        if let Some(&(_, ix)) = parents.first() {
+            return Err(failure::format_err!("Unclosed '(' at offset {}", ix));
+        }
+
         Ok(Building {
             distances,
             connections,
@@ -148,8 +175,71 @@ impl FromStr for Building {
 }
 
 impl Building {
+    /// The number of rooms at each distance from the origin, by distance.
+    pub fn distance_histogram(&self) -> BTreeMap<i64, usize> {
+        let mut histogram = BTreeMap::new();
+        for &d in self.distances.values() {
+            *histogram.entry(d).or_insert(0) += 1;
+        }
+        histogram
+    }
+
     pub fn furthest(&self) -> i64 {
-        self.distances.iter().map(|(_, &v)| v).max().unwrap()
+        *self.distance_histogram().keys().next_back().unwrap()
+    }
+
+    /// The number of rooms whose shortest path from the origin passes through at least n doors.
+    pub fn rooms_at_least(&self, n: i64) -> usize {
+        self.distance_histogram().range(n..).map(|(_, &c)| c).sum()
+    }
+
+    /// The number of rooms whose shortest path from the origin passes through exactly n doors.
+    pub fn rooms_exactly(&self, n: i64) -> usize {
+        self.distance_histogram().get(&n).copied().unwrap_or(0)
+    }
+
+    /// A shortest path from the origin to the furthest room, not including the origin, so its
+    /// length is furthest(). If several rooms (or paths) tie, the smallest rooms are chosen.
+    pub fn path_to_furthest(&self) -> Vec<Room> {
+        let furthest = self.furthest();
+        let target = self
+            .distances
+            .iter()
+            .filter(|&(_, &d)| d == furthest)
+            .map(|(&r, _)| r)
+            .min()
+            .unwrap();
+
+        // Breadth-first search from the origin, noting where we came from
+        let origin = Room::default();
+        let mut came_from: HashMap<Room, Room> = HashMap::new();
+        let mut queue: VecDeque<Room> = VecDeque::new();
+        queue.push_back(origin);
+        while let Some(room) = queue.pop_front() {
+            if room == target {
+                break;
+            }
+            let mut neighbors: Vec<Room> = self.connections[&room].iter().copied().collect();
+            neighbors.sort();
+            for next in neighbors {
+                if next == origin {
+                    continue;
+                }
+                if let Entry::Vacant(v) = came_from.entry(next) {
+                    v.insert(room);
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        let mut path = Vec::new();
+        let mut room = target;
+        while room != origin {
+            path.push(room);
+            room = came_from[&room];
+        }
+        path.reverse();
+        path
     }
 }
 
@@ -178,17 +268,10 @@ fn main() -> Result<(), failure::Error> {
 
     eprintln!("Found string of length {}", line.as_bytes().len());
 
-    let b = Building::from_str(line)?;
+    let b = Building::from_str(line.trim())?;
     println!("Furthest: {}", b.furthest());
 
-    let mut over1000 = 0;
-    for (_, d) in b.distances {
-        if d >= 1000 {
-            over1000 += 1;
-        }
-    }
-
-    println!("Over 1000: {}", over1000);
+    println!("Over 1000: {}", b.rooms_at_least(1000));
 
     Ok(())
 }
@@ -213,6 +296,41 @@ mod tests {
         assert_eq!(b.furthest(), 18);
     }
 
+    #[test]
+    fn test_path_to_furthest() {
+        let s = "^ENWWW(NEEE|SSE(EE|N))$";
+        let b = Building::from_str(s).unwrap();
+        let path = b.path_to_furthest();
+        assert_eq!(path.len() as i64, b.furthest());
+
+        let mut last = Room::default();
+        for &room in &path {
+            assert!(b.connections[&last].contains(&room));
+            last = room;
+        }
+        assert_eq!(b.distances[&last], b.furthest());
+    }
+
+    #[test]
+    fn test_malformed() {
+        let err = Building::from_str("^WN(E$").unwrap_err();
+        assert_eq!(err.to_string(), "Unclosed '(' at offset 3");
+
+        let err = Building::from_str("^WNE)$").unwrap_err();
+        assert_eq!(err.to_string(), "Unmatched ')' at offset 4");
+
+        let err = Building::from_str("^WN|E$").unwrap_err();
+        assert_eq!(err.to_string(), "'|' outside of any group at offset 3");
+
+        let err = Building::from_str("^WNX$").unwrap_err();
+        assert_eq!(err.to_string(), "Unrecognized character 'X' at offset 3");
+
+        assert!(Building::from_str("WNE$").is_err());
+        assert!(Building::from_str("^WNE").is_err());
+        assert!(Building::from_str("^").is_err());
+        assert_eq!(Building::from_str("^$").unwrap().furthest(), 0);
+    }
+
     /// More advanced examples from the problem
     #[test]
     fn test_more() {
@@ -225,6 +343,47 @@ mod tests {
         assert_eq!(b.furthest(), 31);
     }
 
+    #[test]
+    fn test_room_counts() {
+        let s = "^ESSWWN(E|NNENN(EESS(WNSE|)SSS|WWWSSSSE(SW|NNNE)))$";
+        let b = Building::from_str(s).unwrap();
+        assert_eq!(b.rooms_at_least(0), 36);
+        assert_eq!(b.rooms_at_least(10), 25);
+        assert_eq!(b.rooms_at_least(20), 6);
+        assert_eq!(b.rooms_at_least(24), 0);
+        assert_eq!(b.rooms_exactly(0), 1);
+        assert_eq!(b.rooms_exactly(20), 2);
+        assert_eq!(b.rooms_exactly(23), 1);
+
+        let s = "^WSSEESWWWNW(S|NENNEEEENN(ESSSSW(NWSW|SSEN)|WSWWN(E|WWS(E|SS))))$";
+        let b = Building::from_str(s).unwrap();
+        assert_eq!(b.rooms_at_least(5), 44);
+        assert_eq!(b.rooms_at_least(30), 7);
+        assert_eq!(b.rooms_exactly(30), 4);
+        assert_eq!(b.rooms_exactly(31), 3);
+        // Counting doesn't consume the distances
+        assert_eq!(b.distances.len(), 49);
+    }
+
+    #[test]
+    fn test_distance_histogram() {
+        let s = "^ENWWW(NEEE|SSE(EE|N))$";
+        let b = Building::from_str(s).unwrap();
+        let histogram = b.distance_histogram();
+        assert_eq!(histogram.keys().next_back(), Some(&b.furthest()));
+        assert_eq!(histogram.values().sum::<usize>(), b.distances.len());
+        assert_eq!(histogram[&0], 1);
+        for (&d, &count) in &histogram {
+            assert_eq!(count, b.rooms_exactly(d));
+        }
+
+        let s = "^ESSWWN(E|NNENN(EESS(WNSE|)SSS|WWWSSSSE(SW|NNNE)))$";
+        let b = Building::from_str(s).unwrap();
+        let histogram = b.distance_histogram();
+        assert_eq!(histogram.values().sum::<usize>(), 36);
+        assert_eq!(histogram.range(20..).map(|(_, &c)| c).sum::<usize>(), 6);
+    }
+
     /// Extra tests I added
     #[test]
     fn test_extra() {
@@ -1,6 +1,6 @@
 #![warn(clippy::all)]
 
-use aoc::device::{parse_instructions, Device};
+use aoc::device::{parse_instructions, Device, Instruction, OpCode, Value};
 
 use clap::{App, Arg};
 
@@ -9,73 +9,65 @@ use std::fs::File;
 use std::io::prelude::*;
 use std::io::BufReader;
 
-fn device_emulator(x: &mut [i64]) -> Vec<i64> {
-    let mut seen = HashSet::new();
-    let mut ordered = Vec::new();
-
-    x[1] = x[3] | 65536;
-    x[3] = 14_906_355;
-
-    'a: loop {
-        x[4] = x[1] & 255;
-        x[3] = (((x[3] + (x[1] & 255)) & 16_777_215) * 65899) & 16_777_215;
-
-        if 256 > x[1] {
-            // println!("outer loop: {}, {}", x[1], x[3]);
-            ordered.push(x[3]);
-            if !seen.insert((x[1], x[3])) {
-                // println!("Already seen x[3]!");
-                // println!("Values seen: {:?}", ordered);
-                return ordered;
+/// Find the values of register 0 that make the device halt: the first one, which halts it
+/// soonest, and the last new one before they start repeating, which halts it latest.
+///
+/// The program halts when an `eqrr` against register 0 succeeds, so this runs a copy of the
+/// device with register 0 set to something that never matches, and collects the values that
+/// `eqrr` compares it against. With `verbose`, each value is printed as it's found.
+fn halting_values(device: &Device, verbose: bool) -> Result<(Value, Value), failure::Error> {
+    let (check_ix, compared) = device
+        .instructions
+        .iter()
+        .enumerate()
+        .find_map(|(ix, &instr)| match instr {
+            Instruction(OpCode::EqRR, 0, r, _) | Instruction(OpCode::EqRR, r, 0, _) => {
+                Some((ix, r))
             }
+            _ => None,
+        })
+        .ok_or_else(|| failure::err_msg("No eqrr against register 0 found"))?;
+
+    let mut d = Device::new(
+        device.register.values.len(),
+        device.bound,
+        device.instructions.clone(),
+    );
+    d.register.values = device.register.values.clone();
+    d.register.values[0] = -1;
+    d.pointer = device.pointer;
 
-            if x[3] == x[0] {
-                return ordered;
+    let mut seen = HashSet::new();
+    let mut first = None;
+    let mut last = None;
+    let mut steps: u64 = 0;
+    loop {
+        if d.pointer == check_ix {
+            let v = d.register.values[compared];
+            if !seen.insert(v) {
+                if verbose {
+                    eprintln!("Step {}: {} repeats, after {} values", steps, v, seen.len());
+                }
+                break;
             }
-            x[1] = x[3] | 65536;
-            x[3] = 14_906_355;
-            continue;
-        } else {
-            x[4] = 0;
-        }
-        loop {
-            x[2] = if (x[4] + 1) * 256 > x[1] { 1 } else { 0 };
-            if x[2] != 0 {
-                x[1] = x[4];
-                continue 'a;
+            if verbose {
+                eprintln!("Step {}: register[0] = {} would halt", steps, v);
             }
-            x[4] += 1;
+            first = first.or(Some(v));
+            last = Some(v);
         }
-    }
-}
-
-fn find_last(values: Vec<i64>) -> i64 {
-    println!("------------------------------");
-    if values.is_empty() {
-        return 0;
-    }
-    let mut seen = HashSet::new();
-    let mut last = values[0];
-
-    for (i, &v) in values.iter().enumerate() {
-        if !seen.insert(v) {
-            // already seen
-            continue;
-        }
-
-        last = v;
-        if i > 100 {
-            continue;
+        if !d.apply() {
+            break;
         }
-
-        println!("{}: {}", i, v);
+        steps += 1;
     }
 
-    println!("------------------------------");
-    println!("Last: {}", last);
-    println!("------------------------------");
-
-    last
+    match (first, last) {
+        (Some(f), Some(l)) => Ok((f, l)),
+        _ => Err(failure::err_msg(
+            "Device halted without checking register 0",
+        )),
+    }
 }
 
 fn main() -> Result<(), failure::Error> {
@@ -87,66 +79,100 @@ fn main() -> Result<(), failure::Error> {
                 .value_name("INPUT")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("verbose")
+                .short("v")
+                .long("verbose")
+                .help("Describe the program, and each halting value as it's found"),
+        )
         .get_matches();
 
     let input_path = matches.value_of("INPUT").unwrap_or("inputs/day21.txt");
+    let verbose = matches.is_present("verbose");
 
-    eprintln!("Using input {}", input_path);
+    if verbose {
+        eprintln!("Using input {}", input_path);
+    }
 
     let file = File::open(input_path)?;
     let buf_reader = BufReader::new(file);
     let some_lines: std::io::Result<VecDeque<String>> = buf_reader.lines().collect();
     let lines: VecDeque<String> = some_lines?;
     let (pointer, instructions) = parse_instructions(lines)?;
-    println!(
-        "Found pointer {}, instructions {}",
-        pointer,
-        instructions.len()
-    );
-    let mut d = Device::new(6, pointer, instructions.clone());
-    d.register.values[0] = 0;
-    // Register 3 will have this at some point
-    // d.register.values[0] = 3_173_684;
-    // d.register.values[0] = 1_566_402;
-    d.register.values[0] = 13_202_558;
-
-    let mut emulated = d.register.values.clone();
-    let values = device_emulator(emulated.as_mut_slice());
-    find_last(values);
-
-    let max_steps = 1_000_000;
-    // let last_n = 100;
-
-    let mut steps = 0;
-    let mut pointer = d.pointer;
-    let mut instruction = instructions.get(d.pointer);
-    let mut values = d.register.values.clone();
-
-    while d.apply() {
-        // if steps < last_n
-        //     || steps + last_n >= max_steps
-        //     || pointer >= 26
-        //     || d.pointer >= 26
-        //     || pointer < 18
-        //     || d.pointer < 18
-        if pointer == 28 {
-            println!(
-                "{} Pointer {} -> {} ({:?}), {:?} -> {:?}",
-                steps, pointer, d.pointer, instruction, values, d.register.values,
-            );
-        }
-        pointer = d.pointer;
-        instruction = instructions.get(d.pointer);
-        values = d.register.values.clone();
-        steps += 1;
-
-        if steps >= max_steps {
-            println!("Breaking after {} steps: {:?}", steps, d.register.values);
-            break;
-        }
+    if verbose {
+        eprintln!(
+            "Found pointer {}, instructions {}",
+            pointer,
+            instructions.len()
+        );
     }
+    let d = Device::new(6, pointer, instructions);
 
-    println!("Finished after {} steps: {:?}", steps, d.register.values);
+    // Part one, halting after the fewest instructions, then part two, after the most
+    let (first, last) = halting_values(&d, verbose)?;
+    println!("{}", first);
+    println!("{}", last);
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Counts up by 3 (mod 16) until it matches register 0, so any of the 16 values halts it
+    const COUNTER_INPUT: &str = r#"
+        #ip 5
+        seti 0 0 3
+        addi 3 3 3
+        bani 3 15 3
+        eqrr 3 0 4
+        addr 4 5 5
+        seti 0 0 5
+    "#;
+
+    // The same, counting up by 5 (mod 8)
+    const COUNTER_BY_5_INPUT: &str = r#"
+        #ip 5
+        seti 0 0 3
+        addi 3 5 3
+        bani 3 7 3
+        eqrr 3 0 4
+        addr 4 5 5
+        seti 0 0 5
+    "#;
+
+    fn get_test_device() -> Device {
+        device_from(COUNTER_INPUT)
+    }
+
+    fn device_from(input: &str) -> Device {
+        let lines: Vec<&str> = input.split('\n').collect();
+        let (pointer, instructions) = parse_instructions(lines).unwrap();
+        Device::new(6, pointer, instructions)
+    }
+
+    #[test]
+    fn test_halting_values() {
+        let d = get_test_device();
+        let (first, last) = halting_values(&d, false).unwrap();
+        assert_eq!((first, last), (3, 0));
+
+        for &v in &[first, last] {
+            let mut d = get_test_device();
+            d.register.values[0] = v;
+            let mut steps = 0;
+            while d.apply() {
+                steps += 1;
+                assert!(steps < 1000, "Didn't halt with register[0] = {}", v);
+            }
+        }
+    }
+
+    #[test]
+    fn test_halting_values_cycle() {
+        // 5, 2, 7, 4, 1, 6, 3, 0, and then 5 again
+        let d = device_from(COUNTER_BY_5_INPUT);
+        assert_eq!(halting_values(&d, false).unwrap(), (5, 0));
+    }
+}
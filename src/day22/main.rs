@@ -1,8 +1,8 @@
 #![warn(clippy::all)]
 
-use std::cmp::Reverse;
-use std::collections::{BinaryHeap, HashMap};
+use std::collections::HashMap;
 
+use aoc::pathfind::AStar;
 use clap::{App, Arg};
 
 const MODULUS: i64 = 20_183;
@@ -34,6 +34,15 @@ impl Into<char> for Erosion {
     }
 }
 
+fn erosion_from_level(level: i64) -> Erosion {
+    match level % 3 {
+        0 => Erosion::Rocky,
+        1 => Erosion::Wet,
+        2 => Erosion::Narrow,
+        _ => unreachable!(),
+    }
+}
+
 pub type Point = (i64, i64);
 
 pub struct Cave {
@@ -41,6 +50,9 @@ pub struct Cave {
     target: Point,
 
     geologies: Vec<Vec<i64>>,
+    // The erosion type of each stored geology, so routing doesn't redo the division on every
+    // visit
+    erosions: Vec<Vec<Erosion>>,
 }
 
 impl Cave {
@@ -49,6 +61,7 @@ impl Cave {
             depth,
             target,
             geologies: Vec::new(),
+            erosions: Vec::new(),
         }
     }
 
@@ -70,13 +83,31 @@ impl Cave {
         (g + self.depth) % MODULUS
     }
 
+    fn cached_erosion(&self, x: i64, y: i64) -> Option<Erosion> {
+        if x < 0 || y < 0 {
+            return None;
+        }
+        self.erosions
+            .get(x as usize)
+            .and_then(|row| row.get(y as usize))
+            .cloned()
+    }
+
     pub fn erosion(&mut self, x: i64, y: i64) -> Erosion {
-        match self.erosion_level(x, y) % 3 {
-            0 => Erosion::Rocky,
-            1 => Erosion::Wet,
-            2 => Erosion::Narrow,
-            _ => unreachable!(),
+        if let Some(e) = self.cached_erosion(x, y) {
+            return e;
         }
+        // Growing the grid caches (x, y), except on the edges and at the target
+        let level = self.erosion_level(x, y);
+        self.cached_erosion(x, y)
+            .unwrap_or_else(|| erosion_from_level(level))
+    }
+
+    // Append the next geology in row x, along with its erosion type.
+    fn push_geology(&mut self, x: i64, value: i64) {
+        let e = erosion_from_level((value + self.depth) % MODULUS);
+        self.geologies[x as usize].push(value);
+        self.erosions[x as usize].push(e);
     }
 
     fn geology_from_previous(&mut self, x: i64, y: i64) -> i64 {
@@ -96,6 +127,8 @@ impl Cave {
         (e1 * e2) % MODULUS
     }
 
+    // Geology at (target_x, target_y), growing the stored grid out to it as needed, so callers
+    // can ask for any point without precomputing a region first.
     fn geology(&mut self, target_x: i64, target_y: i64) -> i64 {
         let xlen = self.geologies.len();
         let ylen = self.geologies.get(0).map(|v| v.len()).unwrap_or(0);
@@ -112,8 +145,6 @@ impl Cave {
             return self.geology_from_previous(target_x, target_y);
         }
 
-        eprintln!("Calling geology({}, {})", target_x, target_y);
-
         // Fill existing rows out to target_y
         if (ylen as i64) < target_y + 1 {
             for x in 0..xlen as i64 {
@@ -125,7 +156,7 @@ impl Cave {
                     //     x,
                     //     self.geologies[x as usize].len()
                     // );
-                    self.geologies[x as usize].push(value);
+                    self.push_geology(x, value);
                 }
             }
         }
@@ -136,10 +167,11 @@ impl Cave {
         for x in (xlen as i64)..=target_x {
             // eprintln!("Filling new row {} from {}..{}", x, 0, ylen2);
             self.geologies.push(Vec::with_capacity(ylen + 1));
+            self.erosions.push(Vec::with_capacity(ylen + 1));
             for y in 0..ylen2 {
                 // println!("Adding value at ({}, {})", x, y);
                 let value = self.geology_from_previous(x, y);
-                self.geologies[x as usize].push(value);
+                self.push_geology(x, value);
             }
             // println!(
             //     "Adding row {} ({})",
@@ -165,6 +197,26 @@ impl Cave {
         self.geologies[target_x as usize][target_y as usize]
     }
 
+    /// Draw the cave from the mouth out to (width, height), one line per row: `.` for rocky,
+    /// `=` for wet, `|` for narrow, with `M` at the mouth and `T` at the target.
+    pub fn render(&mut self, width: i64, height: i64) -> String {
+        let mut s = String::with_capacity(((width + 1) * height) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                let c: char = if (x, y) == (0, 0) {
+                    'M'
+                } else if (x, y) == self.target {
+                    'T'
+                } else {
+                    self.erosion(x, y).into()
+                };
+                s.push(c);
+            }
+            s.push('\n');
+        }
+        s
+    }
+
     pub fn risk(&mut self) -> i64 {
         let mut sum = 0;
         let (target_x, target_y) = self.target;
@@ -200,79 +252,56 @@ pub type Time = i64;
 
 pub struct Routes {
     target: Point,
-    // (location, tool in hand) -> (time taken, previous, previous tool)
-    seen: HashMap<(Point, Tool), (Time, Point, Tool)>,
-    // Time is expected arrival time
-    queue: BinaryHeap<(Reverse<Time>, Point, Tool)>,
+    // Over (location, tool in hand)
+    search: AStar<(Point, Tool), Time>,
     fastest: Option<Time>,
 }
 
 impl Routes {
+    /// A lower bound on the time left to reach the target with the torch.
+    ///
+    /// Every move covers at most one unit of Manhattan distance, so the distance is a lower
+    /// bound on the moves left. Tool switches are only counted when one is unavoidable: the
+    /// route has to end holding the torch, so any other tool needs at least one switch (7
+    /// minutes). Holding the torch, the route may never need to switch, so nothing is added.
+    /// Both parts are lower bounds, and a single step changes the estimate by no more than it
+    /// costs, so the search never skips past a faster route.
     fn heuristic(&self, point: Point, tool: Tool) -> i64 {
         let distance = (point.0 - self.target.0).abs() + (point.1 - self.target.1).abs();
-        if tool == Tool::Torch {
-            return distance;
-        }
-        distance + 7
+        let switches = if tool == Tool::Torch { 0 } else { 1 };
+        distance + 7 * switches
     }
 
-    fn push(&mut self, current: Time, pt: Point, tool: Tool, prev: Point, prev_tool: Tool) {
-        if let Some(t) = self.fastest {
-            if current > t {
-                return;
-            }
-        }
-
-        if let Some(&(existing_time, _, _)) = self.seen.get(&(pt, tool)) {
-            if existing_time <= current {
-                // We've already been here, and just as fast.
-                return;
-            }
-        }
-
-        self.seen.insert((pt, tool), (current, prev, prev_tool));
+    // Whether a state expected to arrive at the given time could still beat the fastest route
+    // found. As the heuristic never overestimates, a state that can only tie it is no use.
+    fn could_beat_fastest(&self, expected: Time) -> bool {
+        self.fastest.map(|f| expected < f).unwrap_or(true)
+    }
 
-        let expected: Time = current + self.heuristic(pt, tool);
-        if let Some(t) = self.fastest {
-            if expected > t {
-                return;
-            }
+    fn push(&mut self, current: Time, pt: Point, tool: Tool, prev: (Point, Tool)) {
+        let estimate = self.heuristic(pt, tool);
+        if !self.could_beat_fastest(current + estimate) {
+            return;
         }
-        self.queue.push((Reverse(expected), pt, tool));
-        // println!("New Queue: {:?}", self.queue);
-        // queue.sort_by_key(|(pt, tool)| {
-        //     let h = Routes::heuristic(pt, tl, cave.target);
-        //     let time = seen[(pt, tool)].0;
-        //     h + time;
-        // })
+        self.search.push((pt, tool), current, estimate, prev);
     }
 
     pub fn new(cave: &Cave) -> Routes {
-        let mut seen = HashMap::new();
-        let mut queue = BinaryHeap::new();
-
-        let start = ((0, 0), Tool::Torch);
-        seen.insert(start, (0, start.0, start.1));
-        queue.push((Reverse(0), start.0, start.1));
-
         Routes {
             target: cave.target,
-            seen,
-            queue,
+            search: AStar::new(((0, 0), Tool::Torch)),
             fastest: None,
         }
     }
 
     pub fn step(&mut self, cave: &mut Cave) -> bool {
-        let (_, (x, y), tool) = match self.queue.pop() {
+        let (((x, y), tool), time) = match self.search.pop() {
             None => {
                 return false;
             }
             Some(s) => s,
         };
 
-        let (time, _, _) = self.seen[&((x, y), tool)];
-
         if (x, y) == self.target && tool == Tool::Torch {
             let is_faster = self.fastest.map(|f| f > time).unwrap_or(true);
             if is_faster {
@@ -295,10 +324,16 @@ impl Routes {
             return true;
         }
 
-        if let Some(f) = self.fastest {
-            if time > f {
-                // This path takes too long, let's go a different way
-                return true;
+        if !self.could_beat_fastest(time + self.heuristic((x, y), tool)) {
+            // This path takes too long, let's go a different way
+            return true;
+        }
+
+        // Switch to the other tool that works here. That's only allowed for a tool that works
+        // both here and where we're going, so we switch in place, and then move.
+        for &next_tool in &tools(cave.erosion(x, y)) {
+            if next_tool != tool {
+                self.push(time + 7, (x, y), next_tool, ((x, y), tool));
             }
         }
 
@@ -314,36 +349,40 @@ impl Routes {
                 continue;
             }
 
-            let erosion = cave.erosion(nx, ny);
-
-            for &next_tool in &tools(erosion) {
-                let mut next_time = time + 1;
-                if next_tool != tool {
-                    next_time += 7;
-                }
-
-                self.push(next_time, (nx, ny), next_tool, (x, y), tool);
+            // Move, if the tool in hand works there
+            if tools(cave.erosion(nx, ny)).contains(&tool) {
+                self.push(time + 1, (nx, ny), tool, ((x, y), tool));
             }
         }
 
         true
     }
 
+    /// The fastest route found, as (time, location, tool) after each move or tool switch,
+    /// ending at the target with the torch. A switch stays at the same location.
     pub fn route(&self) -> Vec<(Time, Point, Tool)> {
         if self.fastest.is_none() {
             return vec![];
         }
 
-        let mut last = (self.target, Tool::Torch);
-        let mut backtracked = Vec::new();
-        while last != ((0, 0), Tool::Torch) {
-            let (t, prev, prev_tool) = self.seen[&last];
-            backtracked.push((t, last.0, last.1));
-            last = (prev, prev_tool);
-        }
+        let path = self.search.path(&(self.target, Tool::Torch));
+        // The start isn't a move or a switch
+        path.into_iter()
+            .flatten()
+            .skip(1)
+            .map(|((pt, tool), t)| (t, pt, tool))
+            .collect()
+    }
 
-        backtracked.reverse();
-        backtracked
+    /// The fastest time found to the target holding each tool that reached it. Only the
+    /// torch's time is sure to be the minimum; the search stops exploring other routes once
+    /// they can't beat it.
+    pub fn best_times_to_target(&self) -> HashMap<Tool, Time> {
+        self.search
+            .costs()
+            .filter(|&(&(pt, _), _)| pt == self.target)
+            .map(|(&(_, tool), time)| (tool, time))
+            .collect()
     }
 }
 
@@ -388,21 +427,18 @@ fn main() -> Result<(), failure::Error> {
 
     let mut c = Cave::new(depth, (target_x, target_y));
     println!("Risk: {}", c.risk());
-    c.geology(target_x + 500, target_y + 500);
 
     let mut routes = Routes::new(&c);
     let mut step = 0;
     while routes.step(&mut c) {
         step += 1;
         if step % 10_000 == 0 {
-            let &(expected_rev, pt, tool) = routes.queue.peek().unwrap();
-            let expected = expected_rev.0;
-            let (time, _, _) = routes.seen.get(&(pt, tool)).unwrap();
+            let (expected, time, &(pt, tool)) = routes.search.peek().unwrap();
             println!(
                 "Step {}: Seen {}, Queue {}, fastest: {:?}, at ({}, {}) with {:?}; time {} ({} / {})",
                 step,
-                routes.seen.len(),
-                routes.queue.len(),
+                routes.search.seen_len(),
+                routes.search.frontier_len(),
                 routes.fastest,
                 pt.0,
                 pt.1,
@@ -412,12 +448,10 @@ fn main() -> Result<(), failure::Error> {
                 expected,
             );
 
-            let ql = routes.queue.len();
-            if ql > 10 {
-                let mut all: Vec<_> = routes.queue.iter().collect();
+            if routes.search.frontier_len() > 10 {
+                let mut all: Vec<_> = routes.search.frontier().collect();
                 all.sort();
-                let rem = &all[ql - 10..ql];
-                println!("Remaining: {:?}", rem);
+                println!("Remaining: {:?}", &all[..10]);
             }
         }
         if step >= 1_000_000 {
@@ -432,11 +466,7 @@ fn main() -> Result<(), failure::Error> {
     }
 
     let f = routes.fastest.unwrap();
-    println!(
-        "Fastest route: {} or with an extra 1 for no apparent reason: {}",
-        f,
-        f + 1
-    );
+    println!("Fastest route: {}", f);
 
     Ok(())
 }
@@ -526,6 +556,45 @@ M=.|=.|.|=.|=|=.
         assert_eq!(c.risk(), 114);
     }
 
+    #[test]
+    fn test_erosion_cache() {
+        let mut c = Cave::new(510, (10, 10));
+        let mut fresh = Cave::new(510, (10, 10));
+
+        // Grow the grid in a few uneven steps, checking against a fresh level computation
+        for &(x, y) in &[(3, 12), (15, 2), (15, 15), (10, 10), (0, 20), (4, 0)] {
+            let e = c.erosion(x, y);
+            assert_eq!(e, erosion_from_level(fresh.erosion_level(x, y)));
+            // Points on the edges or past the grid aren't stored
+            if let Some(cached) = c.cached_erosion(x, y) {
+                assert_eq!(cached, e);
+            }
+        }
+        assert!(c.cached_erosion(15, 15).is_some());
+        assert_eq!(c.erosions.len(), c.geologies.len());
+        for (row, erosions) in c.geologies.iter().zip(&c.erosions) {
+            assert_eq!(row.len(), erosions.len());
+        }
+    }
+
+    #[test]
+    fn test_render() {
+        let mut c = Cave::new(510, (10, 10));
+
+        let expected: Vec<&str> = EXAMPLE_INPUT
+            .split('\n')
+            .map(|l| l.trim())
+            .filter(|l| !l.is_empty())
+            .take(11)
+            .map(|l| &l[..11])
+            .collect();
+        let rendered = c.render(11, 11);
+        assert_eq!(rendered.lines().collect::<Vec<_>>(), expected);
+
+        let full = c.render(16, 16);
+        assert_eq!(full.trim(), EXAMPLE_INPUT.trim());
+    }
+
     #[test]
     fn test_routing() {
         let mut c = Cave::new(510, (10, 10));
@@ -537,18 +606,107 @@ M=.|=.|.|=.|=|=.
             println!(
                 "Step {}: Queue {}, fastest: {:?}, next: {:?}",
                 step,
-                routes.queue.len(),
+                routes.search.frontier_len(),
                 routes.fastest,
-                routes.queue.peek(),
+                routes.search.peek(),
             );
         }
 
         assert_eq!(routes.fastest, Some(45));
+        assert!(step < 341, "Took {} steps", step);
 
         let route = routes.route();
-        for (time, pt, tool) in route {
+        for &(time, pt, tool) in &route {
             let state = c.erosion(pt.0, pt.1);
             println!("{}: {:?} {:?} {:?}", time, pt, tool, state);
         }
+
+        assert_eq!(route.last(), Some(&(45, (10, 10), Tool::Torch)));
+        let mut last = (0, (0, 0), Tool::Torch);
+        for &next in &route {
+            let (time, (x, y), tool) = last;
+            let (next_time, (nx, ny), next_tool) = next;
+            let distance = (nx - x).abs() + (ny - y).abs();
+            if distance == 0 {
+                // A tool switch, to a tool that works here
+                assert_ne!(tool, next_tool);
+                assert_eq!(next_time, time + 7);
+            } else {
+                // A move, with a tool that works both here and there
+                assert_eq!(distance, 1);
+                assert_eq!(tool, next_tool);
+                assert_eq!(next_time, time + 1);
+            }
+            assert!(tools(c.erosion(x, y)).contains(&next_tool));
+            assert!(tools(c.erosion(nx, ny)).contains(&next_tool));
+            last = next;
+        }
+    }
+
+    #[test]
+    fn test_best_times_to_target() {
+        let mut c = Cave::new(510, (10, 10));
+        let mut routes = Routes::new(&c);
+        while routes.step(&mut c) {}
+
+        let times = routes.best_times_to_target();
+        println!("Times: {:?}", times);
+        assert_eq!(times.get(&Tool::Torch), Some(&45));
+        // The target is rocky, so it can't be reached with neither tool
+        assert!(!times.contains_key(&Tool::Neither));
+        for (&tool, &time) in &times {
+            assert!(tools(c.erosion(10, 10)).contains(&tool));
+            // Otherwise, switching to the torch there would have been faster
+            assert!(time + 7 >= 45, "{:?} took {}", tool, time);
+        }
+    }
+
+    #[test]
+    fn test_shared_astar() {
+        use aoc::pathfind::astar;
+
+        let mut c = Cave::new(510, (10, 10));
+        let target = c.target;
+
+        let start = ((0, 0), Tool::Torch);
+        let goal = (target, Tool::Torch);
+        // Switch tools in place, or move holding a tool that works there
+        let neighbors = |&((x, y), tool): &(Point, Tool)| {
+            let mut next = Vec::new();
+            for &t in &tools(c.erosion(x, y)) {
+                if t != tool {
+                    next.push((((x, y), t), 7));
+                }
+            }
+            for &(dx, dy) in &[(-1, 0), (1, 0), (0, -1), (0, 1)] {
+                let (nx, ny) = (x + dx, y + dy);
+                if nx >= 0 && ny >= 0 && tools(c.erosion(nx, ny)).contains(&tool) {
+                    next.push((((nx, ny), tool), 1));
+                }
+            }
+            next
+        };
+        let heuristic = |&((x, y), tool): &(Point, Tool)| {
+            let distance = (x - target.0).abs() + (y - target.1).abs();
+            distance + if tool == Tool::Torch { 0 } else { 7 }
+        };
+
+        let (time, path): (Time, _) = astar(start, |&n| n == goal, neighbors, heuristic).unwrap();
+        assert_eq!(time, 45);
+        assert_eq!(path.first(), Some(&start));
+        assert_eq!(path.last(), Some(&goal));
+    }
+
+    #[test]
+    fn test_routing_far() {
+        // Nothing is precomputed; the cave grows as the search reaches further out
+        let mut c = Cave::new(510, (30, 30));
+        let mut routes = Routes::new(&c);
+        while routes.step(&mut c) {}
+
+        assert_eq!(routes.fastest, Some(82));
+        // The search reaches x = 33 and y = 31, a little past the target on each side
+        assert_eq!(c.geologies.len(), 34);
+        assert!(c.geologies.iter().all(|row| row.len() == 32));
     }
 }
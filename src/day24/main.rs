@@ -54,11 +54,43 @@ impl Army {
         }
     }
 
-    fn effective_power(&self, boost: i64) -> i64 {
+    // Put the army on a side, as the nth army there (counting from 1), and name it to match.
+    fn place(&mut self, side: Side, n: usize) {
+        self.name = match side {
+            Side::Immune => format!("Immune {}", n),
+            Side::Infection => format!("Infection {}", n),
+            Side::Unknown => "Unknown".to_owned(),
+        };
+        self.side = side;
+    }
+
+    // The boost only applies to the immune system; infection armies ignore it.
+    fn effective_power(&self, immune_boost: i64) -> i64 {
+        let boost = if self.side == Side::Immune {
+            immune_boost
+        } else {
+            0
+        };
         self.units * (self.damage + boost)
     }
 }
 
+/// How a battle ended: the winning side with its remaining units, or a stalemate, when a round
+/// of fighting kills nothing and so every later round would too.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Victory(Side, i64),
+    Stalemate,
+}
+
+/// One attack from a round of fighting, as (attacker, defender, units killed).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct AttackEvent {
+    pub attacker: Index,
+    pub defender: Index,
+    pub killed: i64,
+}
+
 #[derive(Debug, Default, Clone, PartialEq, Eq)]
 pub struct Battle {
     // Maps initiative -> Army
@@ -82,15 +114,7 @@ impl Battle {
     }
 
     pub fn effective_power(&self, ix: Index) -> i64 {
-        let army = &self.armies[&ix];
-
-        let boost = if army.side == Side::Immune {
-            self.boost
-        } else {
-            0
-        };
-
-        (army.damage + boost) * army.units
+        self[ix].effective_power(self.boost)
     }
 
     fn target_order(&self) -> VecDeque<Index> {
@@ -135,6 +159,8 @@ impl Battle {
         damage
     }
 
+    // The enemy this army would deal the most damage to, as (damage, enemy), skipping those in
+    // ignore. Ties go to the enemy with the most effective power, then the highest initiative.
     fn choose_target(&self, attacker: Index, ignore: &HashSet<Index>) -> Option<(i64, Index)> {
         let att_army = &self.armies[&attacker];
         if att_army.units == 0 {
@@ -201,6 +227,17 @@ impl Battle {
 
     // Returns total units killed
     pub fn fight(&mut self) -> i64 {
+        self.fight_logging(None)
+    }
+
+    // Returns total units killed, and each attack made, in attack order
+    pub fn fight_logged(&mut self) -> (i64, Vec<AttackEvent>) {
+        let mut events = Vec::new();
+        let deaths = self.fight_logging(Some(&mut events));
+        (deaths, events)
+    }
+
+    fn fight_logging(&mut self, mut events: Option<&mut Vec<AttackEvent>>) -> i64 {
         let order = self.target_order();
         let mut attacks: HashMap<Index, Index> = HashMap::new();
         let mut attacked: HashSet<Index> = HashSet::new();
@@ -257,6 +294,14 @@ impl Battle {
             let army = self.armies.get_mut(&def).unwrap();
             army.units -= units_killed;
             deaths += units_killed;
+
+            if let Some(evs) = events.as_mut() {
+                evs.push(AttackEvent {
+                    attacker: att,
+                    defender: def,
+                    killed: units_killed,
+                });
+            }
         }
 
         deaths
@@ -283,6 +328,42 @@ impl Battle {
         );
         winner
     }
+
+    pub fn run_to_end(&mut self) -> Outcome {
+        let winner = self.finish();
+        let (imm, inf) = self.units();
+        match winner {
+            Side::Immune => Outcome::Victory(winner, imm),
+            Side::Infection => Outcome::Victory(winner, inf),
+            Side::Unknown => Outcome::Stalemate,
+        }
+    }
+}
+
+/// Find the smallest boost that lets the immune system win, and how many units it has left.
+/// Once the boost is enough for any damaging attack to wipe out a whole infection army, more
+/// can't help, so None is returned if that still isn't enough.
+pub fn minimal_winning_boost(initial: &Battle) -> Option<(i64, i64)> {
+    let max_boost = initial
+        .armies
+        .values()
+        .filter(|a| a.side == Side::Infection)
+        .map(|a| a.units * a.hp)
+        .max()
+        .unwrap_or(0);
+
+    for boost in 0..=max_boost {
+        let mut battle = initial.clone();
+        battle.boost = boost;
+        match battle.run_to_end() {
+            Outcome::Victory(Side::Immune, units) => return Some((boost, units)),
+            // Not a win, but not a loss either: a little more boost may be enough to break it
+            Outcome::Stalemate => info!("Boost {}: stalemate", boost),
+            outcome => info!("Boost {}: {:?}", boost, outcome),
+        }
+    }
+
+    None
 }
 
 impl std::ops::Index<Index> for Battle {
@@ -407,6 +488,15 @@ pub fn parse_army(i: &str) -> IResult<&str, Army> {
     ))
 }
 
+// Parse an army, placed on the given side as its nth army, rather than left Unknown.
+pub fn parse_army_on(side: Side, n: usize) -> impl Fn(&str) -> IResult<&str, Army> {
+    move |i: &str| {
+        let (i, mut army) = parse_army(i)?;
+        army.place(side, n);
+        Ok((i, army))
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 enum PossibleLine {
     Empty,
@@ -463,21 +553,19 @@ where
 
         if state == PossibleLine::Infection {
             infection_seen += 1;
-            army.name = format!("Infection {}", infection_seen);
-            army.side = Side::Infection;
+            army.place(Side::Infection, infection_seen);
             debug!(
                 "Pushing infection: {:?} power: {}",
                 army,
-                army.effective_power(0)
+                army.effective_power(boost)
             );
         } else if state == PossibleLine::Immune {
             immune_seen += 1;
-            army.name = format!("Immune {}", immune_seen);
-            army.side = Side::Immune;
+            army.place(Side::Immune, immune_seen);
             debug!(
                 "Pushing immune: {:?} power: {}",
                 army,
-                army.effective_power(0)
+                army.effective_power(boost)
             );
         } else {
             return Err(failure::err_msg("Expected it to start with army name"));
@@ -489,6 +577,15 @@ where
     Ok(battle)
 }
 
+// A boost given on the command line; negative boosts aren't allowed.
+fn parse_boost(s: &str) -> Result<i64, failure::Error> {
+    let boost: i64 = s.trim().parse()?;
+    if boost < 0 {
+        return Err(failure::format_err!("Boost must be >= 0, got {}", boost));
+    }
+    Ok(boost)
+}
+
 fn main() -> Result<(), failure::Error> {
     env_logger::init();
 
@@ -500,44 +597,49 @@ fn main() -> Result<(), failure::Error> {
                 .value_name("INPUT")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("boost")
+                .short("b")
+                .long("boost")
+                .value_name("BOOST")
+                .help("Boost to the immune system's damage (default 0)")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("rounds")
+                .long("rounds")
+                .help("Print the unit counts after each round"),
+        )
         .get_matches();
 
     let input_path = matches.value_of("INPUT").unwrap_or("inputs/day24.txt");
+    let boost = parse_boost(matches.value_of("boost").unwrap_or("0"))?;
 
     debug!("Using input {}", input_path);
     let file = File::open(input_path)?;
     let buf_reader = BufReader::new(file);
-    let original_battle = parse_lines(buf_reader.lines(), 0)?;
+    let original_battle = parse_lines(buf_reader.lines(), boost)?;
     let mut battle = original_battle.clone();
 
-    loop {
-        let killed = battle.fight();
-        let (imm, inf) = battle.units();
-        info!(
-            "{} Units Killed. {} Immune, {} Infection remain",
-            killed, imm, inf
-        );
-        if killed == 0 {
-            break;
+    if matches.is_present("rounds") {
+        let mut round = 0;
+        while battle.fight() > 0 {
+            round += 1;
+            let (imm, inf) = battle.units();
+            println!("Round {}: immune {}, infection {}", round, imm, inf);
         }
     }
 
-    let (imm, inf) = battle.units();
-    println!("Battle complete. {} Immune, {} Infection remain", imm, inf);
-
-    for boost in 1.. {
-        battle = original_battle.clone();
-        battle.boost = boost;
-        let side = battle.finish();
-        let (imm, inf) = battle.units();
-        info!(
-            "Finished boost {} with {} immune, {} inf units",
-            boost, imm, inf
-        );
-        if side == Side::Immune {
-            println!("Immune won with boost {}, and {} units", boost, imm);
-            break;
+    match battle.run_to_end() {
+        Outcome::Victory(side, units) => {
+            println!("Battle complete. {:?} won with {} units", side, units)
         }
+        Outcome::Stalemate => println!("Battle ended in a stalemate"),
+    }
+
+    match minimal_winning_boost(&original_battle) {
+        Some((boost, units)) => println!("Immune won with boost {}, and {} units", boost, units),
+        None => println!("Immune can't win with any boost"),
     }
 
     Ok(())
@@ -649,6 +751,29 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_army_on() {
+        let line = "989 units each with 1274 hit points (immune to fire; weak to bludgeoning, slashing) with an attack that does 25 slashing damage at initiative 3";
+        let (i, army) = parse_army_on(Side::Immune, 2)(line).unwrap();
+        assert_eq!(i, "");
+        assert_eq!(
+            army,
+            Army {
+                name: "Immune 2".to_owned(),
+                side: Side::Immune,
+                initiative: 3,
+                damage: 25,
+                hp: 1274,
+                reactions: Reactions {
+                    immunities: hs_from_arr(&["fire"]),
+                    weaknesses: hs_from_arr(&["bludgeoning", "slashing"]),
+                },
+                specialty: "slashing".to_owned(),
+                units: 989,
+            }
+        );
+    }
+
     fn get_test_battle(input: &str, boost: i64) -> Result<Battle, failure::Error> {
         let lines: Vec<&str> = input.split('\n').collect();
         parse_lines::<_, failure::Error, _>(lines.iter().map(Ok), boost)
@@ -726,6 +851,43 @@ mod tests {
         assert_eq!(battle[army4.index()], army4);
     }
 
+    #[test]
+    fn test_effective_power() {
+        let battle = get_test_battle(TEST_INPUT, 1570).unwrap();
+
+        // Immune 1 gets the boost
+        let immune = Index { value: 2 };
+        assert_eq!(battle.effective_power(immune), 17 * (4507 + 1570));
+        assert_eq!(
+            battle[immune].effective_power(battle.boost),
+            battle.effective_power(immune)
+        );
+
+        // Infection 1 doesn't
+        let infection = Index { value: 1 };
+        assert_eq!(battle.effective_power(infection), 801 * 116);
+        assert_eq!(
+            battle[infection].effective_power(battle.boost),
+            battle.effective_power(infection)
+        );
+    }
+
+    #[test]
+    fn test_parse_section_order() {
+        // The same armies, with the infection listed first
+        let reversed = r#"
+        Infection:
+        801 units each with 4706 hit points (weak to radiation) with an attack that does 116 bludgeoning damage at initiative 1
+        4485 units each with 2961 hit points (immune to radiation; weak to fire, cold) with an attack that does 12 slashing damage at initiative 4
+
+        Immune System:
+        17 units each with 5390 hit points (weak to radiation, bludgeoning) with an attack that does 4507 fire damage at initiative 2
+        989 units each with 1274 hit points (immune to fire; weak to bludgeoning, slashing) with an attack that does 25 slashing damage at initiative 3
+        "#;
+        let battle = get_test_battle(reversed, 0).unwrap();
+        assert_eq!(battle, get_test_battle(TEST_INPUT, 0).unwrap());
+    }
+
     #[test]
     fn test_target_order() {
         let battle = get_test_battle(TEST_INPUT, 0).unwrap();
@@ -734,6 +896,60 @@ mod tests {
         assert_eq!(order, vec![1, 2, 4, 3]);
     }
 
+    #[test]
+    fn test_choose_target_ties() {
+        // The immune army does 100 damage to every infection army; the last two have the same
+        // effective power
+        let input = r#"
+        Immune System:
+        10 units each with 10 hit points with an attack that does 10 fire damage at initiative 5
+
+        Infection:
+        100 units each with 10 hit points with an attack that does 5 cold damage at initiative 1
+        50 units each with 10 hit points with an attack that does 20 cold damage at initiative 2
+        25 units each with 10 hit points with an attack that does 40 cold damage at initiative 3
+        "#;
+        let battle = get_test_battle(input, 0).unwrap();
+        let attacker = Index { value: 5 };
+        let (weak, strong, stronger) = (Index { value: 1 }, Index { value: 2 }, Index { value: 3 });
+        assert_eq!(battle.effective_power(weak), 500);
+        assert_eq!(battle.effective_power(strong), 1000);
+        assert_eq!(battle.effective_power(stronger), 1000);
+
+        // Equal damage and power, so the higher initiative wins
+        let mut ignore = HashSet::new();
+        assert_eq!(
+            battle.choose_target(attacker, &ignore),
+            Some((100, stronger))
+        );
+        // Equal damage, so the higher effective power wins
+        ignore.insert(stronger);
+        assert_eq!(battle.choose_target(attacker, &ignore), Some((100, strong)));
+        ignore.insert(strong);
+        assert_eq!(battle.choose_target(attacker, &ignore), Some((100, weak)));
+        ignore.insert(weak);
+        assert_eq!(battle.choose_target(attacker, &ignore), None);
+
+        // Damage comes first: a weakness makes the weakest army the target
+        let weak_to_fire = input.replacen(
+            "10 hit points with an attack that does 5",
+            "10 hit points (weak to fire) with an attack that does 5",
+            1,
+        );
+        let battle = get_test_battle(&weak_to_fire, 0).unwrap();
+        let ignore = HashSet::new();
+        assert_eq!(battle.choose_target(attacker, &ignore), Some((200, weak)));
+
+        // Equal damage after reactions: both weak, so again the higher effective power wins
+        let both_weak = weak_to_fire.replacen(
+            "10 hit points with an attack that does 20",
+            "10 hit points (weak to fire) with an attack that does 20",
+            1,
+        );
+        let battle = get_test_battle(&both_weak, 0).unwrap();
+        assert_eq!(battle.choose_target(attacker, &ignore), Some((200, strong)));
+    }
+
     #[test]
     fn test_attack_order() {
         let battle = get_test_battle(TEST_INPUT, 0).unwrap();
@@ -796,6 +1012,30 @@ mod tests {
         assert_eq!(battle[Index { value: 4 }].units, 4434);
     }
 
+    #[test]
+    fn test_fight_logged() {
+        let mut battle = get_test_battle(TEST_INPUT, 0).unwrap();
+
+        let event = |attacker, defender, killed| AttackEvent {
+            attacker: Index { value: attacker },
+            defender: Index { value: defender },
+            killed,
+        };
+
+        let (deaths, events) = battle.fight_logged();
+        assert_eq!(
+            events,
+            vec![
+                event(4, 3, 989 - 905),
+                event(3, 1, 801 - 797),
+                event(2, 4, 4485 - 4434),
+                event(1, 2, 17),
+            ]
+        );
+        assert_eq!(deaths, events.iter().map(|e| e.killed).sum::<i64>());
+        assert_eq!(battle[Index { value: 2 }].units, 0);
+    }
+
     #[test]
     fn test_boost_fight() {
         let mut battle = get_test_battle(TEST_INPUT, 1570).unwrap();
@@ -895,4 +1135,74 @@ mod tests {
         assert_eq!(imm, 51);
         assert_eq!(inf, 0);
     }
+
+    #[test]
+    fn test_run_to_end() {
+        let mut battle = get_test_battle(TEST_INPUT, 0).unwrap();
+        assert_eq!(
+            battle.run_to_end(),
+            Outcome::Victory(Side::Infection, 782 + 4434)
+        );
+
+        let mut battle = get_test_battle(TEST_INPUT, 1570).unwrap();
+        assert_eq!(battle.run_to_end(), Outcome::Victory(Side::Immune, 51));
+
+        // Each side attacks, but neither can do enough damage to kill a single unit
+        let stalemate = r#"
+            Immune System:
+            10 units each with 100 hit points with an attack that does 5 fire damage at initiative 1
+
+            Infection:
+            10 units each with 100 hit points with an attack that does 5 cold damage at initiative 2
+        "#;
+        let mut battle = get_test_battle(stalemate, 0).unwrap();
+        assert_eq!(battle.run_to_end(), Outcome::Stalemate);
+        assert_eq!(battle.units(), (10, 10));
+    }
+
+    #[test]
+    fn test_mutual_immunity() {
+        let immune = r#"
+            Immune System:
+            10 units each with 100 hit points (immune to cold) with an attack that does 50 fire damage at initiative 1
+
+            Infection:
+            10 units each with 100 hit points (immune to fire) with an attack that does 50 cold damage at initiative 2
+        "#;
+        let mut battle = get_test_battle(immune, 0).unwrap();
+        assert_eq!(battle.fight(), 0);
+        assert_eq!(battle.run_to_end(), Outcome::Stalemate);
+
+        // No boost helps, and the search gives up rather than looping forever
+        let battle = get_test_battle(immune, 0).unwrap();
+        assert_eq!(minimal_winning_boost(&battle), None);
+    }
+
+    #[test]
+    fn test_parse_boost() {
+        let boost = parse_boost("1570").unwrap();
+        let mut battle = get_test_battle(TEST_INPUT, boost).unwrap();
+        assert_eq!(battle.run_to_end(), Outcome::Victory(Side::Immune, 51));
+
+        assert_eq!(parse_boost("0").unwrap(), 0);
+        assert!(parse_boost("-1").is_err());
+        assert!(parse_boost("lots").is_err());
+    }
+
+    #[test]
+    fn test_minimal_winning_boost() {
+        let battle = get_test_battle(TEST_INPUT, 0).unwrap();
+        assert_eq!(minimal_winning_boost(&battle), Some((1570, 51)));
+
+        // The immune system can't hurt the infection at all
+        let hopeless = r#"
+            Immune System:
+            10 units each with 100 hit points with an attack that does 5 fire damage at initiative 1
+
+            Infection:
+            1 units each with 10 hit points (immune to fire) with an attack that does 5 cold damage at initiative 2
+        "#;
+        let battle = get_test_battle(hopeless, 0).unwrap();
+        assert_eq!(minimal_winning_boost(&battle), None);
+    }
 }
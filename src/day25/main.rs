@@ -42,75 +42,138 @@ impl Vec4 {
     }
 }
 
+// Disjoint sets of point ids, with path compression and union by rank.
+#[derive(Debug, Default, Clone)]
+struct UnionFind {
+    parents: Vec<usize>,
+    ranks: Vec<usize>,
+}
+
+impl UnionFind {
+    // Add a new singleton set, returning its id
+    fn push(&mut self) -> usize {
+        let id = self.parents.len();
+        self.parents.push(id);
+        self.ranks.push(0);
+        id
+    }
+
+    // Like find, but without compressing the path
+    fn root(&self, id: usize) -> usize {
+        let mut root = id;
+        while self.parents[root] != root {
+            root = self.parents[root];
+        }
+        root
+    }
+
+    fn find(&mut self, id: usize) -> usize {
+        let root = self.root(id);
+
+        // Point everything on the way directly at the root
+        let mut cur = id;
+        while cur != root {
+            let next = self.parents[cur];
+            self.parents[cur] = root;
+            cur = next;
+        }
+
+        root
+    }
+
+    // Merge the sets containing a and b. Returns (root, merged root), or None if they were
+    // already the same set.
+    fn union(&mut self, a: usize, b: usize) -> Option<(usize, usize)> {
+        let (ra, rb) = (self.find(a), self.find(b));
+        if ra == rb {
+            return None;
+        }
+
+        let (root, child) = if self.ranks[ra] < self.ranks[rb] {
+            (rb, ra)
+        } else {
+            (ra, rb)
+        };
+        self.parents[child] = root;
+        if self.ranks[root] == self.ranks[child] {
+            self.ranks[root] += 1;
+        }
+
+        Some((root, child))
+    }
+}
+
 pub struct Constellations {
-    // (constellation id, point)
-    points: Vec<(usize, Vec4)>,
-    // Maps constellation id -> Vec<Point id>
+    // Points at most this far apart are in the same constellation
+    threshold: Val,
+    points: Vec<Vec4>,
+    sets: UnionFind,
+    // Maps constellation id (the root of its set) -> Vec<Point id>
     constellations: HashMap<usize, Vec<usize>>,
 }
 
 impl Constellations {
-    pub fn add(&mut self, v: Vec4) {
-        let mut my_constellations: Vec<usize> = Vec::new();
-        for &(c, v2) in &self.points {
-            let d = (v2 - v).manhattan();
-            if d <= 3 {
-                my_constellations.push(c);
-            }
+    pub fn with_threshold(t: Val) -> Self {
+        Constellations {
+            threshold: t,
+            points: Vec::new(),
+            sets: UnionFind::default(),
+            constellations: HashMap::new(),
         }
+    }
+
+    /// The id of the constellation containing the given point.
+    pub fn constellation_of(&self, point_index: usize) -> usize {
+        self.sets.root(point_index)
+    }
+
+    /// The points in a constellation, or nothing if there's no such constellation.
+    pub fn members(&self, constellation_id: usize) -> &[usize] {
+        self.constellations
+            .get(&constellation_id)
+            .map_or(&[][..], Vec::as_slice)
+    }
+
+    /// The number of points in the biggest constellation, or 0 if there are no points.
+    pub fn largest(&self) -> usize {
+        self.constellations
+            .values()
+            .map(Vec::len)
+            .max()
+            .unwrap_or(0)
+    }
 
-        let id = self.points.len();
+    pub fn add(&mut self, v: Vec4) {
+        let id = self.sets.push();
         debug!("Adding point {}: {:?}", id, v);
-        if my_constellations.is_empty() {
-            debug!("  New constellation {} with point {}", id, id);
-            self.points.push((id, v));
-            self.constellations.insert(id, vec![id]);
-            return;
-        }
+        self.points.push(v);
+        self.constellations.insert(id, vec![id]);
 
-        my_constellations.sort_unstable();
-        my_constellations.dedup();
-
-        let mn: usize = my_constellations[0];
-        debug!("  Joining constellation {}", mn);
-
-        for c in &my_constellations[1..] {
-            debug!("  Merging constellation {} -> constellation {}", c, mn);
-            // Merge constellations
-            let mut merging = self.constellations.remove(&c).unwrap();
-            // Update each point in the main vec
-            for &vid in &merging {
-                debug!("    Merging point {} -> constellation {}", vid, mn);
-                let (_, vc) = self.points[vid];
-                self.points[vid] = (mn, vc);
+        for other in 0..id {
+            let d = (self.points[other] - v).manhattan();
+            if d > self.threshold {
+                continue;
             }
-            // Merge maps in the main hashmap
-            let new_c = self.constellations.get_mut(&mn).unwrap();
-            new_c.append(&mut merging);
-        }
 
-        // Add current point to the constellation
-        debug!("  Adding point {} -> constellation {}", id, mn);
-        let new_c = self.constellations.get_mut(&mn).unwrap();
-        new_c.push(id);
-        new_c.sort_unstable();
-        // Push point onto the main stack
-        self.points.push((mn, v));
+            if let Some((root, merged)) = self.sets.union(id, other) {
+                debug!(
+                    "  Merging constellation {} -> constellation {}",
+                    merged, root
+                );
+                let mut merging = self.constellations.remove(&merged).unwrap();
+                self.constellations
+                    .get_mut(&root)
+                    .unwrap()
+                    .append(&mut merging);
+            }
+        }
     }
 }
 
 impl FromIterator<Vec4> for Constellations {
     fn from_iter<T: IntoIterator<Item = Vec4>>(iter: T) -> Self {
-        let it = iter.into_iter();
-        let (mn, mx) = it.size_hint();
-        let sz = if let Some(m) = mx { m } else { mn };
-
-        let mut constellations = Constellations {
-            points: Vec::with_capacity(sz),
-            constellations: HashMap::new(),
-        };
-
-        for v in it {
+        let mut constellations = Constellations::with_threshold(3);
+        for v in iter {
             constellations.add(v)
         }
 
@@ -140,6 +203,7 @@ fn main() -> Result<(), failure::Error> {
     let c = Constellations::from_iter(points);
 
     println!("Found {} constellations", c.constellations.len());
+    println!("The largest has {} points", c.largest());
 
     Ok(())
 }
@@ -233,6 +297,118 @@ mod tests {
         ),
     ];
 
+    // Count constellations by flood-filling the graph of points within distance 3
+    fn naive_count(pts: &[Vec4]) -> usize {
+        let mut seen = vec![false; pts.len()];
+        let mut count = 0;
+        for start in 0..pts.len() {
+            if seen[start] {
+                continue;
+            }
+            count += 1;
+            seen[start] = true;
+            let mut stack = vec![start];
+            while let Some(i) = stack.pop() {
+                for (j, &p) in pts.iter().enumerate() {
+                    if !seen[j] && (pts[i] - p).manhattan() <= 3 {
+                        seen[j] = true;
+                        stack.push(j);
+                    }
+                }
+            }
+        }
+        count
+    }
+
+    #[test]
+    fn test_union_find() {
+        let mut sets = UnionFind::default();
+        for _ in 0..5 {
+            sets.push();
+        }
+        assert_eq!(sets.union(0, 1), Some((0, 1)));
+        assert_eq!(sets.union(2, 1), Some((0, 2)));
+        assert_eq!(sets.union(2, 0), None);
+        assert_eq!(sets.find(2), 0);
+        assert_eq!(sets.find(3), 3);
+        assert_eq!(sets.find(4), 4);
+    }
+
+    #[test]
+    fn test_matches_naive() {
+        let pts = parse_str(Vec4::parse_line, INPUT1).unwrap();
+        let c = Constellations::from_iter(pts.clone());
+        assert_eq!(c.constellations.len(), naive_count(&pts));
+
+        for &(n, s) in MORE_INPUTS {
+            let pts = parse_str(Vec4::parse_line, s).unwrap();
+            let c = Constellations::from_iter(pts.clone());
+            assert_eq!(naive_count(&pts), n);
+            assert_eq!(c.constellations.len(), naive_count(&pts));
+
+            let members: usize = c.constellations.values().map(Vec::len).sum();
+            assert_eq!(members, pts.len());
+        }
+    }
+
+    #[test]
+    fn test_membership() {
+        let pts = parse_str(Vec4::parse_line, INPUT1).unwrap();
+        let mut c = Constellations::from_iter(pts);
+
+        let first = c.constellation_of(0);
+        let last = c.constellation_of(7);
+        assert_ne!(first, last);
+        assert_eq!(c.members(first).len(), 6);
+        assert_eq!(c.members(last).len(), 2);
+
+        c.add(Vec4(6, 0, 0, 0));
+        let id = c.constellation_of(0);
+        for i in 0..8 {
+            assert_eq!(c.constellation_of(i), id);
+        }
+        let mut members = c.members(id).to_vec();
+        members.sort_unstable();
+        assert_eq!(members, (0..9).collect::<Vec<usize>>());
+        assert!(c.members(id + 100).is_empty());
+    }
+
+    #[test]
+    fn test_largest() {
+        assert_eq!(Constellations::with_threshold(3).largest(), 0);
+
+        let pts = parse_str(Vec4::parse_line, INPUT1).unwrap();
+        assert_eq!(Constellations::from_iter(pts).largest(), 6);
+
+        // Of the 8 constellations, two are pairs and the rest single points
+        let (n, s) = MORE_INPUTS[2];
+        assert_eq!(n, 8);
+        let pts = parse_str(Vec4::parse_line, s).unwrap();
+        let c = Constellations::from_iter(pts);
+        assert_eq!(c.largest(), 2);
+        let pairs = c.constellations.values().filter(|m| m.len() == 2).count();
+        assert_eq!(pairs, 2);
+    }
+
+    #[test]
+    fn test_threshold() {
+        let pts = parse_str(Vec4::parse_line, INPUT1).unwrap();
+        let default = Constellations::from_iter(pts.clone());
+
+        let mut c = Constellations::with_threshold(6);
+        for &p in &pts {
+            c.add(p);
+        }
+        assert_eq!(default.constellations.len(), 2);
+        assert_eq!(c.constellations.len(), 1);
+
+        let mut c = Constellations::with_threshold(3);
+        for &p in &pts {
+            c.add(p);
+        }
+        assert_eq!(c.constellations.len(), default.constellations.len());
+    }
+
     #[test]
     fn test_constellation_creation() {
         let pts = parse_str(Vec4::parse_line, INPUT1).unwrap();
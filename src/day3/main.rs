@@ -67,6 +67,35 @@ impl Claims {
     fn overlap_area(&self) -> i64 {
         self.overlaps.iter().map(|o| o.area()).sum()
     }
+
+    /// Draw the top-left `width` x `height` of the fabric: `.` is unclaimed, a digit is a
+    /// single claim (the last digit of its id), and `X` is claimed more than once.
+    fn render(&self, width: i16, height: i16) -> String {
+        // (number of claims, last claim id) at each point
+        let mut counts = vec![vec![(0, 0); width.max(0) as usize]; height.max(0) as usize];
+        for claim in &self.claims {
+            let r = claim.rect;
+            for y in max(r.top, 0)..min(r.bottom, height) {
+                for x in max(r.left, 0)..min(r.right, width) {
+                    let cell = &mut counts[y as usize][x as usize];
+                    *cell = (cell.0 + 1, claim.id);
+                }
+            }
+        }
+
+        let mut s = String::new();
+        for row in counts {
+            for (n, id) in row {
+                s.push(match n {
+                    0 => '.',
+                    1 => std::char::from_digit((id % 10) as u32, 10).unwrap(),
+                    _ => 'X',
+                });
+            }
+            s.push('\n');
+        }
+        s
+    }
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
@@ -203,6 +232,14 @@ fn main() -> std::io::Result<()> {
                 .value_name("INPUT")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("render")
+                .short("r")
+                .long("render")
+                .value_name("SIZE")
+                .help("Print a map of the top-left SIZE x SIZE of the fabric")
+                .takes_value(true),
+        )
         .get_matches();
 
     let input_path = matches.value_of("INPUT").unwrap_or("inputs/day3.txt");
@@ -220,6 +257,13 @@ fn main() -> std::io::Result<()> {
         println!("No overlap: {}", id);
     }
 
+    if let Some(size) = matches.value_of("render") {
+        let size: i16 = size
+            .parse()
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+        print!("{}", claims.render(size, size));
+    }
+
     Ok(())
 }
 
@@ -266,4 +310,26 @@ mod tests {
         let claims = Claims::from_iter(inputs);
         assert_eq!(claims.overlap_area(), 4);
     }
+
+    #[test]
+    fn test_render() {
+        let inputs = vec![
+            "#1 @ 1,3: 4x4",
+            "#2 @ 3,1: 4x4",
+            "#3 @ 5,5: 2x2",
+            "#4 @ 3,3: 2x2",
+        ];
+        let claims = Claims::from_iter(inputs);
+        let rendered = claims.render(8, 8);
+        let expected = vec![
+            "........", "...2222.", "...2222.", ".11XX22.", ".11XX22.", ".111133.", ".111133.",
+            "........",
+        ];
+        assert_eq!(rendered.lines().collect::<Vec<_>>(), expected);
+
+        let xs = rendered.chars().filter(|&c| c == 'X').count() as i64;
+        assert_eq!(xs, claims.overlap_area());
+
+        assert_eq!(claims.render(3, 2), "...\n...\n");
+    }
 }
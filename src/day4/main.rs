@@ -39,11 +39,21 @@ enum LogLine {
     Guard(Date, u16),
 }
 
+impl LogLine {
+    fn date(self) -> Date {
+        match self {
+            LogLine::WakeUp(d) => d,
+            LogLine::FallAsleep(d) => d,
+            LogLine::Guard(d, _) => d,
+        }
+    }
+}
+
+// Lines are ordered by when they happened. Date's fields go from most to least significant, so
+// it sorts chronologically as is.
 impl Ord for LogLine {
     fn cmp(&self, other: &LogLine) -> Ordering {
-        let date: NaiveDateTime = (*self).into();
-        let other_date: NaiveDateTime = (*other).into();
-        date.cmp(&other_date)
+        self.date().cmp(&other.date())
     }
 }
 
@@ -55,9 +65,7 @@ impl PartialOrd for LogLine {
 
 impl PartialEq for LogLine {
     fn eq(&self, other: &LogLine) -> bool {
-        let date: NaiveDateTime = (*self).into();
-        let other_date: NaiveDateTime = (*other).into();
-        date == other_date
+        self.date() == other.date()
     }
 }
 
@@ -65,13 +73,7 @@ impl Eq for LogLine {}
 
 impl Into<NaiveDateTime> for LogLine {
     fn into(self) -> NaiveDateTime {
-        let d = match self {
-            LogLine::WakeUp(d) => d,
-            LogLine::FallAsleep(d) => d,
-            LogLine::Guard(d, _) => d,
-        };
-
-        d.into()
+        self.date().into()
     }
 }
 
@@ -337,30 +339,30 @@ fn main() -> std::io::Result<()> {
 mod tests {
     use super::*;
 
+    const LINES: &[&str] = &[
+        "[1518-11-01 00:00] Guard #10 begins shift",
+        "[1518-11-01 00:05] falls asleep",
+        "[1518-11-01 00:25] wakes up",
+        "[1518-11-01 00:30] falls asleep",
+        "[1518-11-01 00:55] wakes up",
+        "[1518-11-01 23:58] Guard #99 begins shift",
+        "[1518-11-02 00:40] falls asleep",
+        "[1518-11-02 00:50] wakes up",
+        "[1518-11-03 00:05] Guard #10 begins shift",
+        "[1518-11-03 00:24] falls asleep",
+        "[1518-11-03 00:29] wakes up",
+        "[1518-11-04 00:02] Guard #99 begins shift",
+        "[1518-11-04 00:36] falls asleep",
+        "[1518-11-04 00:46] wakes up",
+        "[1518-11-05 00:03] Guard #99 begins shift",
+        "[1518-11-05 00:45] falls asleep",
+        "[1518-11-05 00:55] wakes up",
+    ];
+
     #[test]
     fn test_build() {
-        let lines = vec![
-            "[1518-11-01 00:00] Guard #10 begins shift",
-            "[1518-11-01 00:05] falls asleep",
-            "[1518-11-01 00:25] wakes up",
-            "[1518-11-01 00:30] falls asleep",
-            "[1518-11-01 00:55] wakes up",
-            "[1518-11-01 23:58] Guard #99 begins shift",
-            "[1518-11-02 00:40] falls asleep",
-            "[1518-11-02 00:50] wakes up",
-            "[1518-11-03 00:05] Guard #10 begins shift",
-            "[1518-11-03 00:24] falls asleep",
-            "[1518-11-03 00:29] wakes up",
-            "[1518-11-04 00:02] Guard #99 begins shift",
-            "[1518-11-04 00:36] falls asleep",
-            "[1518-11-04 00:46] wakes up",
-            "[1518-11-05 00:03] Guard #99 begins shift",
-            "[1518-11-05 00:45] falls asleep",
-            "[1518-11-05 00:55] wakes up",
-        ];
-
-        let log = Log::from_iter(&lines);
-        assert_eq!(log.lines.len(), lines.len());
+        let log = Log::from_iter(LINES);
+        assert_eq!(log.lines.len(), LINES.len());
 
         let shifts: Shifts = log.into();
 
@@ -378,4 +380,26 @@ mod tests {
         assert_eq!(3, count);
         assert_eq!(45, minute);
     }
+
+    #[test]
+    fn test_sort_order() {
+        let lines: Vec<LogLine> = LINES
+            .iter()
+            .map(|l| LogLine::from_str(l).unwrap())
+            .collect();
+        let dates = |ls: &[LogLine]| ls.iter().map(|&l| l.date()).collect::<Vec<_>>();
+
+        // Shuffle, by stepping through the lines 7 at a time (17 lines, so each is hit once)
+        let mut shuffled: Vec<LogLine> = (0..lines.len())
+            .map(|i| lines[(i * 7) % lines.len()])
+            .collect();
+        assert_ne!(dates(&shuffled), dates(&lines));
+
+        let mut by_datetime = shuffled.clone();
+        by_datetime.sort_by_key(|&l| -> NaiveDateTime { l.into() });
+        shuffled.sort();
+
+        assert_eq!(dates(&shuffled), dates(&by_datetime));
+        assert_eq!(dates(&shuffled), dates(&lines));
+    }
 }
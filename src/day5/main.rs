@@ -15,18 +15,33 @@ impl Default for Chemical {
     }
 }
 
+// The usual rule: the same letter, in opposite cases, annihilates.
+fn opposite_case(l: char, c: char) -> bool {
+    l != c && l.to_ascii_lowercase() == c.to_ascii_lowercase()
+}
+
 impl Chemical {
     fn new() -> Self {
         Default::default()
     }
 
     fn react<I: IntoIterator<Item = char>>(&mut self, chars: I) {
+        self.react_with(chars, opposite_case)
+    }
+
+    // Like react, but annihilates(l, c) decides whether a unit c destroys the unit l just
+    // before it.
+    fn react_with<I, F>(&mut self, chars: I, annihilates: F)
+    where
+        I: IntoIterator<Item = char>,
+        F: Fn(char, char) -> bool,
+    {
         let mut last: Option<char> = self.units.pop();
 
         for c in chars {
             last = match last {
                 None => Some(c),
-                Some(l) if (l == c || l.to_ascii_lowercase() != c.to_ascii_lowercase()) => {
+                Some(l) if !annihilates(l, c) => {
                     self.units.push(l);
                     Some(c)
                 }
@@ -116,6 +131,24 @@ mod tests {
         assert_eq!(c.units, "dabCBAcaDA");
     }
 
+    #[test]
+    fn test_react_with() {
+        let s = "dabAcCaCBAcCcaDA";
+        let mut c = Chemical::new();
+        c.react_with(s.chars(), opposite_case);
+        assert_eq!(c.units, "dabCBAcaDA");
+
+        // Matched brackets annihilate, leaving only what's unmatched
+        let brackets = |l: char, c: char| l == '(' && c == ')';
+        let mut c = Chemical::new();
+        c.react_with("(()())x((())".chars(), brackets);
+        assert_eq!(c.units, "x(");
+
+        let mut c = Chemical::new();
+        c.react_with(")(".chars(), brackets);
+        assert_eq!(c.units, ")(");
+    }
+
     #[test]
     fn test_drop_react() {
         let s = "dabAcCaCBAcCcaDA";
@@ -6,11 +6,13 @@ extern crate lazy_static;
 #[macro_use]
 extern crate failure;
 
+use aoc::geom::BoundingBox;
 use clap::{App, Arg};
 use std::collections::HashMap;
 use std::fs::File;
 use std::io::prelude::*;
 use std::io::BufReader;
+use std::num::ParseIntError;
 use std::str::FromStr;
 
 #[derive(Copy, Clone, Hash, PartialEq, Eq, Debug, Default)]
@@ -20,8 +22,12 @@ struct Point(i64, i64);
 enum ParseError {
     #[fail(display = "invalid line: {}", line)]
     LineError { line: String },
-    #[fail(display = "invalid match for {}: {}", part, line)]
-    MatchError { part: String, line: String },
+    #[fail(display = "invalid number in {}: {}", line, source)]
+    MatchError {
+        #[fail(cause)]
+        source: ParseIntError,
+        line: String,
+    },
 }
 
 impl ParseError {
@@ -31,9 +37,9 @@ impl ParseError {
         }
     }
 
-    fn from_part<P: ToString, L: ToString>(part: &P, line: &L) -> ParseError {
+    fn from_part<L: ToString>(source: ParseIntError, line: &L) -> ParseError {
         ParseError::MatchError {
-            part: part.to_string(),
+            source,
             line: line.to_string(),
         }
     }
@@ -53,13 +59,13 @@ impl FromStr for Point {
             .ok_or_else(|| ParseError::from_line(&s))?
             .as_str()
             .parse::<i64>()
-            .map_err(|m| ParseError::from_part(&m, &s))?;
+            .map_err(|m| ParseError::from_part(m, &s))?;
         let y = c
             .get(2)
             .ok_or_else(|| ParseError::from_line(&s))?
             .as_str()
             .parse::<i64>()
-            .map_err(|m| ParseError::from_part(&m, &s))?;
+            .map_err(|m| ParseError::from_part(m, &s))?;
 
         Ok(Point(x, y))
     }
@@ -97,6 +103,8 @@ impl Points {
         Ok(Points(maybe_points?))
     }
 
+    // The closest point to p by checking every point, or None for a tie; voronoi should agree.
+    #[cfg(test)]
     fn find_closest(&self, p: Point) -> Option<Point> {
         let Points(ref ps) = self;
 
@@ -122,41 +130,124 @@ impl Points {
         closest
     }
 
-    fn count_distances(&self) -> HashMap<Point, Option<i64>> {
-        let mut h = HashMap::new();
-        let Points(ref ps) = self;
-        if ps.is_empty() {
-            return h;
+    fn bounding_box(&self) -> Option<BoundingBox> {
+        BoundingBox::from_points(self.0.iter().map(|&Point(x, y)| (x, y)))
+    }
+
+    /// The closest point to every location in the bounding box, or None where there's a tie.
+    ///
+    /// This is a breadth-first search outwards from all the points at once. The points closest
+    /// to a location are those closest to its neighbors one step nearer, so a location goes to
+    /// a point only if all those neighbors do, and is a tie otherwise.
+    fn voronoi(&self) -> HashMap<(i64, i64), Option<Point>> {
+        // location -> (distance, closest point)
+        let mut closest: HashMap<(i64, i64), (i64, Option<Point>)> = HashMap::new();
+        let bbox = match self.bounding_box() {
+            None => return HashMap::new(),
+            Some(b) => b,
+        };
+
+        let mut frontier = Vec::new();
+        for &p in &self.0 {
+            let Point(x, y) = p;
+            // Two points in the same place are tied
+            let owner = if closest.contains_key(&(x, y)) {
+                None
+            } else {
+                frontier.push((x, y));
+                Some(p)
+            };
+            closest.insert((x, y), (0, owner));
         }
 
-        let Point(x0, y0) = ps[0];
+        let mut distance = 0;
+        while !frontier.is_empty() {
+            distance += 1;
+            let mut next = Vec::new();
+            for (x, y) in frontier {
+                let owner = closest[&(x, y)].1;
+                for &loc in &[(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)] {
+                    if !bbox.contains(loc) {
+                        continue;
+                    }
+                    match closest.get_mut(&loc) {
+                        None => {
+                            closest.insert(loc, (distance, owner));
+                            next.push(loc);
+                        }
+                        Some((d, o)) if *d == distance && *o != owner => *o = None,
+                        Some(_) => {}
+                    }
+                }
+            }
+            frontier = next;
+        }
 
-        let (mut x_min, mut x_max, mut y_min, mut y_max): (i64, i64, i64, i64) = (x0, x0, y0, y0);
+        closest.into_iter().map(|(loc, (_, p))| (loc, p)).collect()
+    }
 
-        for &Point(x, y) in ps {
-            x_min = x_min.min(x);
-            x_max = x_max.max(x);
-            y_min = y_min.min(y);
-            y_max = y_max.max(y);
+    /// Draw the bounding box, as in the puzzle: each location is labeled with the closest point
+    /// (`a` for the first, `b` for the second, and so on, wrapping after `z`), in uppercase at
+    /// the point itself, or `.` where there's a tie.
+    fn render(&self) -> String {
+        let bbox = match self.bounding_box() {
+            None => return String::new(),
+            Some(b) => b,
+        };
+
+        let regions = self.voronoi();
+        // Each point's letter comes from where it first appears in the list
+        let mut indices: HashMap<Point, usize> = HashMap::new();
+        for (ix, &p) in self.0.iter().enumerate() {
+            indices.entry(p).or_insert(ix);
         }
 
-        for x in x_min..=x_max {
-            for y in y_min..=y_max {
-                let p = match self.find_closest(Point(x, y)) {
-                    None => continue,
-                    Some(p) => p,
-                };
-
-                let is_edge = x == x_min || x == x_max || y == y_min || y == y_max;
+        let mut s = String::new();
+        for y in bbox.min.1..=bbox.max.1 {
+            for x in bbox.min.0..=bbox.max.0 {
+                let loc = Point(x, y);
+                let closest = regions[&(x, y)];
+                let ix = closest.map(|p| indices[&p]);
+                s.push(match ix {
+                    None => '.',
+                    Some(ix) => {
+                        let c = (b'a' + (ix % 26) as u8) as char;
+                        if closest == Some(loc) {
+                            c.to_ascii_uppercase()
+                        } else {
+                            c
+                        }
+                    }
+                });
+            }
+            s.push('\n');
+        }
+        s
+    }
 
-                if is_edge {
-                    h.insert(p, None);
-                    continue;
-                }
-                h.entry(p)
-                    .and_modify(|o| *o = o.map(|n| n + 1))
-                    .or_insert(Some(1));
+    fn count_distances(&self) -> HashMap<Point, Option<i64>> {
+        let mut h = HashMap::new();
+        let bbox = match self.bounding_box() {
+            None => return h,
+            Some(b) => b,
+        };
+        let ((x_min, y_min), (x_max, y_max)) = (bbox.min, bbox.max);
+
+        for ((x, y), closest) in self.voronoi() {
+            let p = match closest {
+                None => continue,
+                Some(p) => p,
+            };
+
+            let is_edge = x == x_min || x == x_max || y == y_min || y == y_max;
+
+            if is_edge {
+                h.insert(p, None);
+                continue;
             }
+            h.entry(p)
+                .and_modify(|o| *o = o.map(|n| n + 1))
+                .or_insert(Some(1));
         }
 
         h
@@ -212,6 +303,12 @@ fn main() -> Result<(), failure::Error> {
                 .value_name("INPUT")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("render")
+                .short("r")
+                .long("render")
+                .help("Print a map of which point each location is closest to"),
+        )
         .get_matches();
 
     let input_path = matches.value_of("INPUT").unwrap_or("inputs/day6.txt");
@@ -223,6 +320,10 @@ fn main() -> Result<(), failure::Error> {
 
     let points = Points::parse_lines(buf_reader.lines())?;
 
+    if matches.is_present("render") {
+        print!("{}", points.render());
+    }
+
     let ds = points.count_distances();
     let max_a = ds.values().filter_map(|&v| v).max();
     match max_a {
@@ -239,6 +340,7 @@ fn main() -> Result<(), failure::Error> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use failure::Fail;
 
     #[test]
     fn test_point_from_str() {
@@ -246,6 +348,15 @@ mod tests {
         assert_eq!(Point(112, 3), p.unwrap());
     }
 
+    #[test]
+    fn test_point_overflow() {
+        let err = Point::from_str("99999999999999999999, 1").unwrap_err();
+        assert!(err.to_string().contains("too large"), "{}", err);
+
+        let cause = err.cause().expect("Should keep the ParseIntError");
+        assert!(format!("{:?}", cause).contains("Overflow"), "{:?}", cause);
+    }
+
     fn str_ok(s: &str) -> Result<&str, failure::Error> {
         Ok(s)
     }
@@ -262,4 +373,47 @@ mod tests {
         let total_a = points.find_area(32);
         assert_eq!(16, total_a);
     }
+
+    #[test]
+    fn test_voronoi() {
+        let test_input = vec!["1, 1", "1, 6", "8, 3", "3, 4", "5, 5", "8, 9"];
+        let points = Points::parse_lines(test_input.iter().map(|&s| str_ok(s))).unwrap();
+
+        let voronoi = points.voronoi();
+        assert_eq!(voronoi.len(), 8 * 9);
+        for (&(x, y), &closest) in &voronoi {
+            assert_eq!(closest, points.find_closest(Point(x, y)), "At {:?}", (x, y));
+        }
+        // Equally far from (1, 1) and (5, 5)
+        assert_eq!(voronoi[&(5, 1)], None);
+        assert_eq!(voronoi[&(5, 2)], Some(Point(5, 5)));
+
+        // Points in the same place are tied
+        let twins = Points(vec![Point(0, 0), Point(0, 0), Point(3, 0)]);
+        let voronoi = twins.voronoi();
+        assert_eq!(voronoi[&(0, 0)], None);
+        assert_eq!(voronoi[&(1, 0)], None);
+        assert_eq!(voronoi[&(2, 0)], Some(Point(3, 0)));
+    }
+
+    #[test]
+    fn test_render() {
+        let test_input = vec!["1, 1", "1, 6", "8, 3", "3, 4", "5, 5", "8, 9"];
+        let points = Points::parse_lines(test_input.iter().map(|&s| str_ok(s))).unwrap();
+
+        // The puzzle's illustration, cut down to the bounding box
+        let expected = vec![
+            "Aaaa.ccc", "aaddeccc", "adddeccC", ".dDdeecc", "b.deEeec", "Bb.eeee.", "bb.eeeff",
+            "bb.eefff", "bb.ffffF",
+        ];
+        let rendered = points.render();
+        assert_eq!(rendered.lines().collect::<Vec<_>>(), expected);
+
+        // Ties are exactly where find_closest finds none
+        let ties = rendered.chars().filter(|&c| c == '.').count();
+        let voronoi = points.voronoi();
+        assert_eq!(ties, voronoi.values().filter(|p| p.is_none()).count());
+
+        assert_eq!(Points(vec![]).render(), "");
+    }
 }
@@ -82,80 +82,58 @@ impl Graph {
     }
 
     fn breadth_first(&self) -> Vec<String> {
-        let mut deps = self.as_maps();
-
-        let mut ready: Vec<String> = Vec::new();
+        let mut walker = TopoWalker::new(self);
         let mut finished: Vec<String> = Vec::new();
-        for (n, ps) in &deps.parents {
-            if ps.is_empty() {
-                ready.push(n.clone());
-            }
-        }
 
-        while !ready.is_empty() {
-            // Keep it reverse sorted, so we can pop the earliest-by-alphabetical element
-            #[allow(clippy::unnecessary_sort_by)]
-            // This lint wants us to use sort_by_key (or sort_unstable_by_key),
-            // but that doesn't work with references; its a lifetime/HKT thing,
-            // see https://github.com/rust-lang/rust/issues/34162
-            ready.sort_unstable_by(|n1, n2| n2.cmp(n1));
-            // ready.sort_unstable_by_key(|n| std::cmp::Reverse(n));
-            let n = ready.pop().unwrap();
-            finished.push(n.clone());
-            deps.parents.remove(&n);
-            let children: HashSet<String> = deps.children.remove(&n).unwrap();
-            for c in children {
-                let ps = deps.parents.get_mut(&c).unwrap();
-                ps.remove(&n);
-                if ps.is_empty() {
-                    ready.push(c.clone());
-                }
-            }
-        }
-
-        if !deps.parents.is_empty() || !deps.children.is_empty() {
-            panic!(
-                "Didn't empty dependency lists! Still left: {}, {}",
-                deps.parents.len(),
-                deps.children.len()
-            )
+        while let Some(n) = walker.next_ready() {
+            walker.complete(&n);
+            finished.push(n);
         }
 
+        walker.check_done();
         finished
     }
 
-    fn time(s: &str) -> i64 {
-        let bs = s.as_bytes();
-        let a = b"A";
-        i64::from(bs[0] - a[0] + 1)
+    /// The time a step takes on top of the base time, as in the puzzle: 1 for A, 2 for B, and so
+    /// on. Only single uppercase letters have a time.
+    fn time(s: &str) -> Option<i64> {
+        match s.as_bytes() {
+            &[b] if b.is_ascii_uppercase() => Some(i64::from(b - b'A' + 1)),
+            _ => None,
+        }
     }
 
-    fn process(&self, workers: usize, base_time: i64) -> (i64, Vec<String>) {
-        let mut deps = self.as_maps();
+    /// The time taken for the workers to finish every step, and the order they finish in, with
+    /// each step taking `base_time` plus its `time`. Fails if any step isn't a letter A-Z.
+    fn process(
+        &self,
+        workers: usize,
+        base_time: i64,
+    ) -> Result<(i64, Vec<String>), failure::Error> {
+        let mut durations: HashMap<String, i64> = HashMap::new();
+        for name in self.as_maps().parents.keys() {
+            let t = Graph::time(name).ok_or_else(|| {
+                failure::format_err!("No time for step {:?}; it isn't a letter A-Z", name)
+            })?;
+            durations.insert(name.clone(), base_time + t);
+        }
 
-        let mut ready: Vec<String> = Vec::new();
+        Ok(self.process_with(workers, |n| durations[n]))
+    }
+
+    /// Like `process`, but with each step taking `duration(step)`, so steps can have any name.
+    fn process_with<F: Fn(&str) -> i64>(&self, workers: usize, duration: F) -> (i64, Vec<String>) {
+        let mut walker = TopoWalker::new(self);
         let mut finished: Vec<String> = Vec::new();
-        for (n, ps) in &deps.parents {
-            if ps.is_empty() {
-                ready.push(n.clone());
-            }
-        }
 
         // (time finished, job)
         let mut processing: Vec<(i64, String)> = vec![];
         let mut t = 0;
 
-        while !ready.is_empty() || !processing.is_empty() {
-            // Keep it reverse sorted, so we can pop the earliest-by-alphabetical element
-            #[allow(clippy::unnecessary_sort_by)]
-            // This lint wants us to use sort_by_key (or sort_unstable_by_key),
-            // but that doesn't work with references; its a lifetime/HKT thing,
-            // see https://github.com/rust-lang/rust/issues/34162
-            ready.sort_unstable_by(|n1, n2| n2.cmp(n1));
-
-            if let Some(n) = ready.pop() {
+        while walker.has_ready() || !processing.is_empty() {
+            if let Some(n) = walker.next_ready() {
                 // We have a job ready
-                processing.push((Graph::time(&n) + base_time + t, n));
+                processing.push((duration(&n) + t, n));
                 if processing.len() < workers {
                     continue;
                 }
@@ -167,19 +145,68 @@ impl Graph {
             t = processing.last().unwrap().0;
             while !processing.is_empty() && processing.last().unwrap().0 == t {
                 let (_, fin) = processing.pop().unwrap();
-                deps.parents.remove(&fin);
-                let children: HashSet<String> = deps.children.remove(&fin).unwrap();
-                for c in children {
-                    let ps = deps.parents.get_mut(&c).unwrap();
-                    ps.remove(&fin);
-                    if ps.is_empty() {
-                        ready.push(c.clone());
-                    }
-                }
+                walker.complete(&fin);
                 finished.push(fin);
             }
         }
 
+        walker.check_done();
+        (t, finished)
+    }
+}
+
+// Walks the graph in dependency order: hands out nodes whose parents have all completed,
+// earliest alphabetically first, and unlocks their children as they complete.
+struct TopoWalker {
+    deps: DependencyMaps,
+    // Nodes with no parents left, but not yet handed out
+    ready: Vec<String>,
+}
+
+impl TopoWalker {
+    fn new(graph: &Graph) -> Self {
+        let deps = graph.as_maps();
+
+        let mut ready: Vec<String> = Vec::new();
+        for (n, ps) in &deps.parents {
+            if ps.is_empty() {
+                ready.push(n.clone());
+            }
+        }
+
+        TopoWalker { deps, ready }
+    }
+
+    fn has_ready(&self) -> bool {
+        !self.ready.is_empty()
+    }
+
+    // The earliest-by-alphabetical node that's ready, if any.
+    fn next_ready(&mut self) -> Option<String> {
+        // Keep it reverse sorted, so we can pop the earliest-by-alphabetical element
+        #[allow(clippy::unnecessary_sort_by)]
+        // This lint wants us to use sort_by_key (or sort_unstable_by_key),
+        // but that doesn't work with references; its a lifetime/HKT thing,
+        // see https://github.com/rust-lang/rust/issues/34162
+        self.ready.sort_unstable_by(|n1, n2| n2.cmp(n1));
+        self.ready.pop()
+    }
+
+    // Mark a node as done, making any children with no other parents left ready.
+    fn complete(&mut self, n: &str) {
+        self.deps.parents.remove(n);
+        let children: HashSet<String> = self.deps.children.remove(n).unwrap();
+        for c in children {
+            let ps = self.deps.parents.get_mut(&c).unwrap();
+            ps.remove(n);
+            if ps.is_empty() {
+                self.ready.push(c);
+            }
+        }
+    }
+
+    fn check_done(&self) {
+        let deps = &self.deps;
         if !deps.parents.is_empty() || !deps.children.is_empty() {
             panic!(
                 "Didn't empty dependency lists! Still left: {}, {}",
@@ -187,8 +214,6 @@ impl Graph {
                 deps.children.len()
             )
         }
-
-        (t, finished)
     }
 }
 
@@ -203,7 +228,7 @@ impl<'a, S: AsRef<str>> FromIterator<S> for Graph {
     }
 }
 
-fn main() -> std::io::Result<()> {
+fn main() -> Result<(), failure::Error> {
     let matches = App::new("Day 7")
         .arg(
             Arg::with_name("input")
@@ -227,7 +252,7 @@ fn main() -> std::io::Result<()> {
 
     println!("Order: {}", finished.join(""));
 
-    let (t, finished) = graph.process(5, 60);
+    let (t, finished) = graph.process(5, 60)?;
     println!("Finishes in {}: {}", t, finished.join(""));
 
     Ok(())
@@ -267,8 +292,90 @@ mod tests {
         ];
 
         let graph = Graph::from_iter(lines);
-        let (t, finished) = graph.process(2, 0);
+        let (t, finished) = graph.process(2, 0).unwrap();
         assert_eq!("CABFDE", finished.join(""));
         assert_eq!(t, 15);
     }
+
+    #[test]
+    fn test_topo_walker() {
+        let lines = vec![
+            "Step C must be finished before step A can begin.",
+            "Step C must be finished before step F can begin.",
+            "Step A must be finished before step B can begin.",
+            "Step A must be finished before step D can begin.",
+            "Step B must be finished before step E can begin.",
+            "Step D must be finished before step E can begin.",
+            "Step F must be finished before step E can begin.",
+        ];
+
+        let graph = Graph::from_iter(lines);
+        let mut walker = TopoWalker::new(&graph);
+
+        assert_eq!(walker.next_ready(), Some("C".to_owned()));
+        // Nothing else can start until C is done
+        assert!(!walker.has_ready());
+        assert_eq!(walker.next_ready(), None);
+
+        walker.complete("C");
+        assert_eq!(walker.next_ready(), Some("A".to_owned()));
+        assert_eq!(walker.next_ready(), Some("F".to_owned()));
+        assert_eq!(walker.next_ready(), None);
+
+        // E needs B, D, and F
+        walker.complete("F");
+        walker.complete("A");
+        assert_eq!(walker.next_ready(), Some("B".to_owned()));
+        walker.complete("B");
+        assert_eq!(walker.next_ready(), Some("D".to_owned()));
+        assert_eq!(walker.next_ready(), None);
+        walker.complete("D");
+        assert_eq!(walker.next_ready(), Some("E".to_owned()));
+        walker.complete("E");
+
+        assert!(!walker.has_ready());
+        walker.check_done();
+    }
+
+    #[test]
+    fn test_time() {
+        assert_eq!(Graph::time("A"), Some(1));
+        assert_eq!(Graph::time("Z"), Some(26));
+        assert_eq!(Graph::time("a"), None);
+        assert_eq!(Graph::time("AA"), None);
+        assert_eq!(Graph::time(""), None);
+    }
+
+    #[test]
+    fn test_named_steps() {
+        let lines = vec![
+            "Step AA must be finished before step BB can begin.",
+            "Step AA must be finished before step cc can begin.",
+            "Step BB must be finished before step dd_1 can begin.",
+            "Step cc must be finished before step dd_1 can begin.",
+        ];
+
+        let dep = Dependency::from_str(lines[0]).unwrap();
+        assert_eq!(dep.parent, "AA");
+        assert_eq!(dep.child, "BB");
+        assert!(Dependency::from_str("Step  must be finished before step A can begin.").is_err());
+        assert!(Dependency::from_str("Step A must be finished before step  can begin.").is_err());
+
+        let graph = Graph::from_iter(lines);
+        assert_eq!(graph.breadth_first(), vec!["AA", "BB", "cc", "dd_1"]);
+
+        let costs: HashMap<&str, i64> =
+            HashMap::from_iter(vec![("AA", 5), ("BB", 10), ("cc", 3), ("dd_1", 1)]);
+        let (t, finished) = graph.process_with(2, |n| costs[n]);
+        assert_eq!(finished, vec!["AA", "cc", "BB", "dd_1"]);
+        assert_eq!(t, 16);
+
+        // With one worker, it's the total
+        let (t, _) = graph.process_with(1, |n| costs[n]);
+        assert_eq!(t, 19);
+
+        // Only letters have a time of their own
+        let err = graph.process(2, 0).unwrap_err();
+        assert!(err.to_string().contains("isn't a letter A-Z"), "{}", err);
+    }
 }
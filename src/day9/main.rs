@@ -1,123 +1,68 @@
 #![warn(clippy::all)]
 
-use std::collections::VecDeque;
-
-struct Game {
-    marbles: VecDeque<i64>,
-    marble: i64,
-    scores: Vec<i64>,
-}
-
-impl Game {
-    fn new(players: usize) -> Game {
-        let mut ms = VecDeque::new();
-        ms.push_back(0);
-        Game {
-            marbles: ms,
-            marble: 1,
-            scores: vec![0; players],
-        }
-    }
-
-    fn rotate(&mut self, dist: isize) {
-        if self.marbles.len() < 2 {
-            return;
-        }
-        for _ in 0..dist {
-            let m = self.marbles.pop_back().unwrap();
-            self.marbles.push_front(m);
-        }
-
-        for _ in 0..-dist {
-            let m = self.marbles.pop_front().unwrap();
-            self.marbles.push_back(m);
-        }
+use aoc::days::day9::Game;
+use clap::{App, Arg};
+
+fn main() -> Result<(), failure::Error> {
+    let matches = App::new("Day 9")
+        .arg(
+            Arg::with_name("players")
+                .short("p")
+                .long("players")
+                .value_name("PLAYERS")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("last-marble")
+                .short("l")
+                .long("last-marble")
+                .value_name("LASTMARBLE")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("multiplier")
+                .short("m")
+                .long("multiplier")
+                .value_name("MULTIPLIER")
+                .takes_value(true),
+        )
+        .get_matches();
+
+    let players: usize = matches.value_of("players").unwrap_or("459").parse()?;
+    let last_marble: usize = matches.value_of("last-marble").unwrap_or("71320").parse()?;
+    let multiplier: usize = matches.value_of("multiplier").unwrap_or("100").parse()?;
+
+    if players == 0 {
+        failure::bail!("Need at least one player");
     }
-
-    fn next(&mut self) {
-        if self.marble % 23 == 0 {
-            self.rotate(-7);
-            let removed = self.marbles.pop_back().unwrap();
-            let player = (self.marble as usize) % (self.scores.len());
-
-            self.scores[player] += self.marble + removed;
-            self.marble += 1;
-            return;
-        }
-
-        self.rotate(2);
-        self.marbles.push_back(self.marble);
-        self.marble += 1;
+    if last_marble < 1 {
+        failure::bail!("Last marble must be worth at least 1 point");
     }
-
-    fn play(&mut self, rounds: usize) {
-        for _ in 0..rounds {
-            self.next();
-        }
+    if multiplier < 1 {
+        failure::bail!("Multiplier must be at least 1");
     }
 
-    fn high_score(&self) -> i64 {
-        match self.scores.iter().max() {
-            Some(&n) => n,
-            None => 0,
-        }
-    }
-}
+    eprintln!(
+        "Using {} players; last marble is worth {} points",
+        players, last_marble
+    );
 
-fn main() -> std::io::Result<()> {
-    let mut g = Game::new(459);
-    g.play(71320);
+    let mut g = Game::new(players);
+    g.play(last_marble);
     println!("High score: {:?}", g.high_score());
 
-    let mut g = Game::new(459);
-    g.play(7_132_000);
-    println!("High score after 100x more rounds: {:?}", g.high_score());
+    // Carry on with the same game rather than starting over
+    g.play(last_marble * (multiplier - 1));
+    eprintln!(
+        "Played {} marbles; current marble is {}",
+        g.marbles_played(),
+        g.current_marble()
+    );
+    println!(
+        "High score with {}x as many marbles: {:?}",
+        multiplier,
+        g.high_score()
+    );
 
     Ok(())
 }
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_game() {
-        let mut g = Game::new(9);
-        g.play(25);
-
-        // Below is the order from the site.
-        // Actual order in the data structure is reversed with the "active" marble
-        // at the end.
-        // assert_eq!(
-        //     g.marbles,
-        //     vec!(
-        //         0, 16, 8, 17, 4, 18, 19, 2, 24, 20, 25, 10, 21, 5, 22, 11, 1, 12, 6, 13, 3, 14, 7,
-        //         15
-        //     )
-        // );
-        assert_eq!(g.high_score(), 32);
-    }
-
-    #[test]
-    fn test_high_scores() {
-        let mut g = Game::new(10);
-        g.play(1618);
-        assert_eq!(g.high_score(), 8317);
-
-        g = Game::new(13);
-        g.play(7999);
-        assert_eq!(g.high_score(), 146_373);
-
-        g = Game::new(17);
-        g.play(1104);
-        assert_eq!(g.high_score(), 2764);
-
-        g = Game::new(21);
-        g.play(6111);
-        assert_eq!(g.high_score(), 54_718);
-
-        g = Game::new(30);
-        g.play(5807);
-        assert_eq!(g.high_score(), 37305);
-    }
-}
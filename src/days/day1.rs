@@ -0,0 +1,85 @@
+use std::collections::HashSet;
+
+fn parse(input: &str) -> Result<Vec<i64>, failure::Error> {
+    crate::input::from_str(input)
+        .map(|l| {
+            l.parse::<i64>()
+                .map_err(|e| failure::format_err!("Invalid change {:?}: {}", l, e))
+        })
+        .collect()
+}
+
+// The first running total reached twice, going round the changes as many times as needed.
+fn first_repeat(values: &[i64]) -> Result<i64, failure::Error> {
+    if values.is_empty() {
+        failure::bail!("No frequency changes given");
+    }
+
+    // Each time round shifts every running total by the sum of the changes. The first repeat
+    // is of a total from the first time round, so once the totals have been shifted across the
+    // whole range the first time round covered, there can't be one.
+    let total: i64 = values.iter().sum();
+    let (lo, hi) = values
+        .iter()
+        .scan(0, |sum, &v| {
+            *sum += v;
+            Some(*sum)
+        })
+        .fold((0, 0), |(lo, hi), sum| (lo.min(sum), hi.max(sum)));
+    let rounds = if total == 0 {
+        1
+    } else {
+        (hi - lo) / total.abs() + 1
+    };
+
+    let mut seen: HashSet<i64> = HashSet::new();
+    let mut sum = 0;
+    seen.insert(sum);
+    for _ in 0..rounds {
+        for &v in values {
+            sum += v;
+            if !seen.insert(sum) {
+                return Ok(sum);
+            }
+        }
+    }
+
+    failure::bail!("No frequency is reached twice")
+}
+
+/// The final frequency, and the first frequency reached twice.
+pub fn solve(input: &str) -> Result<(String, String), failure::Error> {
+    let values = parse(input)?;
+    let sum: i64 = values.iter().sum();
+    Ok((sum.to_string(), first_repeat(&values)?.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_solve() {
+        assert_eq!(
+            solve("+1\n-2\n+3\n+1\n").unwrap(),
+            ("3".to_owned(), "2".to_owned())
+        );
+        assert_eq!(first_repeat(&[1, -1]).unwrap(), 0);
+        assert_eq!(first_repeat(&[3, 3, 4, -2, -4]).unwrap(), 10);
+        assert_eq!(first_repeat(&[-6, 3, 8, 5, -6]).unwrap(), 5);
+        assert_eq!(first_repeat(&[7, 7, -2, -7, -4]).unwrap(), 14);
+    }
+
+    #[test]
+    fn test_errors() {
+        let err = solve("+1\n-x\n").unwrap_err();
+        assert!(err.to_string().contains("-x"), "{}", err);
+
+        assert!(first_repeat(&[]).is_err());
+        // Always climbing, so nothing repeats
+        assert!(first_repeat(&[1]).is_err());
+        assert!(first_repeat(&[3, -1]).is_err());
+        // 10 is only reached again after going round 9 more times
+        assert_eq!(first_repeat(&[10, -9]).unwrap(), 10);
+    }
+}
@@ -0,0 +1,289 @@
+use std::cmp::{Ordering, Reverse};
+
+#[cfg(test)]
+use itertools::iproduct;
+
+#[derive(Copy, Clone, Debug, PartialEq, PartialOrd)]
+pub struct Grid(pub i64);
+
+/// A square and its total power. These are ordered so that the best square is the greatest:
+/// highest power, then smallest size, then smallest `x`, then smallest `y`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct MaxPower {
+    pub x: i64,
+    pub y: i64,
+    pub size: i64,
+    pub power: i64,
+}
+
+impl MaxPower {
+    fn key(self) -> (i64, Reverse<i64>, Reverse<i64>, Reverse<i64>) {
+        (
+            self.power,
+            Reverse(self.size),
+            Reverse(self.x),
+            Reverse(self.y),
+        )
+    }
+
+    /// Pick the better of the two.
+    fn best(self, other: MaxPower) -> MaxPower {
+        self.max(other)
+    }
+}
+
+impl Ord for MaxPower {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+impl PartialOrd for MaxPower {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Grid {
+    fn power(self, x: i64, y: i64) -> i64 {
+        let rack_id = x + 10;
+        let power1 = ((rack_id * y) + self.0) * rack_id;
+        let hundreds_digit = (power1 % 1000) / 100;
+        hundreds_digit - 5
+    }
+
+    /// The total power of the `w` x `h` rectangle with top-left corner `(x, y)`, summed cell by
+    /// cell. This is only a reference for testing `rect_power`, which does the same in O(1).
+    #[cfg(test)]
+    fn cell_power(self, x: i64, y: i64, w: i64, h: i64) -> i64 {
+        iproduct!(0..w, 0..h)
+            .map(|(dx, dy)| self.power(x + dx, y + dy))
+            .sum()
+    }
+
+    /// The summed-area table of the power: `table[x][y]` is the total power
+    /// of all cells from `(1, 1)` through `(x, y)`, inclusive. Row and column
+    /// 0 are all zeros, so the table is 301x301.
+    pub fn summed_area_table(self) -> Vec<Vec<i64>> {
+        let mut table = vec![vec![0; 301]; 301];
+        for x in 1..=300 {
+            for y in 1..=300 {
+                table[x][y] = self.power(x as i64, y as i64) + table[x - 1][y] + table[x][y - 1]
+                    - table[x - 1][y - 1];
+            }
+        }
+        table
+    }
+
+    /// The square of the given size with the most power.
+    pub fn max_power(self, size: i64) -> MaxPower {
+        self.max_power_in(&self.summed_area_table(), size)
+    }
+
+    fn max_power_in(self, table: &[Vec<i64>], size: i64) -> MaxPower {
+        let mut max = MaxPower {
+            x: 1,
+            y: 1,
+            size,
+            power: rect_power(table, 1, 1, size, size),
+        };
+        for x in 1..=300 - size {
+            for y in 1..=300 - size {
+                let power = rect_power(table, x, y, size, size);
+                if power > max.power {
+                    max = MaxPower { x, y, size, power }
+                }
+            }
+        }
+
+        max
+    }
+
+    #[cfg(not(feature = "rayon"))]
+    pub fn max_up_to_power(self, max_size: i64) -> MaxPower {
+        self.max_up_to_power_seq(max_size)
+    }
+
+    #[cfg(feature = "rayon")]
+    pub fn max_up_to_power(self, max_size: i64) -> MaxPower {
+        self.max_up_to_power_par(max_size)
+    }
+
+    #[cfg_attr(feature = "rayon", allow(dead_code))]
+    fn max_up_to_power_seq(self, max_size: i64) -> MaxPower {
+        let table = self.summed_area_table();
+        (1..=max_size)
+            .map(|size| self.max_power_in(&table, size))
+            .fold(None, |max, current| match max {
+                Some(m) => Some(MaxPower::best(m, current)),
+                None => Some(current),
+            })
+            .expect("There should be at least one")
+    }
+
+    /// The same as `max_up_to_power_seq`, but checks each size in parallel.
+    #[cfg(feature = "rayon")]
+    fn max_up_to_power_par(self, max_size: i64) -> MaxPower {
+        use rayon::prelude::*;
+
+        let table = self.summed_area_table();
+        (1..=max_size)
+            .into_par_iter()
+            .map(|size| self.max_power_in(&table, size))
+            .reduce_with(MaxPower::best)
+            .expect("There should be at least one")
+    }
+}
+
+/// The total power of the `w` x `h` rectangle with top-left corner `(x, y)`,
+/// using a table from `Grid::summed_area_table`.
+pub fn rect_power(table: &[Vec<i64>], x: i64, y: i64, w: i64, h: i64) -> i64 {
+    let (x0, y0) = ((x - 1) as usize, (y - 1) as usize);
+    let (x1, y1) = (x0 + w as usize, y0 + h as usize);
+    table[x1][y1] - table[x0][y1] - table[x1][y0] + table[x0][y0]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_grid_power() {
+        assert_eq!(Grid(8).power(3, 5), 4);
+        assert_eq!(Grid(57).power(122, 79), -5);
+        assert_eq!(Grid(39).power(217, 196), 0);
+        assert_eq!(Grid(71).power(101, 153), 4);
+    }
+
+    #[test]
+    fn test_summed_area_table() {
+        let g = Grid(18);
+        let table = g.summed_area_table();
+        assert_eq!(table.len(), 301);
+        assert_eq!(table[0][17], 0);
+        assert_eq!(table[5][0], 0);
+        assert_eq!(table[1][1], g.power(1, 1));
+        assert_eq!(table[300][300], g.cell_power(1, 1, 300, 300));
+
+        for &(x, y, w, h) in &[
+            (1, 1, 3, 3),
+            (33, 45, 3, 3),
+            (90, 269, 16, 16),
+            (7, 200, 40, 3),
+        ] {
+            assert_eq!(rect_power(&table, x, y, w, h), g.cell_power(x, y, w, h));
+        }
+        assert_eq!(rect_power(&table, 33, 45, 3, 3), 29);
+    }
+
+    #[test]
+    fn test_rect_power_random() {
+        let g = Grid(18);
+        let table = g.summed_area_table();
+
+        // A small linear congruential generator, so the squares are the same on every run
+        let mut state: u64 = 2018;
+        let mut next = |n: i64| {
+            state = state
+                .wrapping_mul(6_364_136_223_846_793_005)
+                .wrapping_add(1_442_695_040_888_963_407);
+            ((state >> 33) % n as u64) as i64
+        };
+        for _ in 0..50 {
+            let size = next(300) + 1;
+            let (x, y) = (next(301 - size) + 1, next(301 - size) + 1);
+            assert_eq!(
+                rect_power(&table, x, y, size, size),
+                g.cell_power(x, y, size, size),
+                "{}x{} square at ({}, {})",
+                size,
+                size,
+                x,
+                y
+            );
+        }
+    }
+
+    #[test]
+    fn test_max_power() {
+        assert_eq!(
+            Grid(18).max_power(3),
+            MaxPower {
+                x: 33,
+                y: 45,
+                power: 29,
+                size: 3
+            }
+        );
+        assert_eq!(
+            Grid(42).max_power(3),
+            MaxPower {
+                x: 21,
+                y: 61,
+                power: 30,
+                size: 3,
+            }
+        );
+    }
+
+    #[test]
+    fn test_max_power_ord() {
+        let weak = MaxPower {
+            x: 300,
+            y: 300,
+            size: 1,
+            power: 5,
+        };
+        let strong = MaxPower {
+            x: 1,
+            y: 1,
+            size: 3,
+            power: 9,
+        };
+        assert_eq!([weak, strong].iter().max(), Some(&strong));
+        assert_eq!([strong, weak].iter().max(), Some(&strong));
+
+        // Equal power: the smaller size wins, then the smaller x, then the smaller y
+        let bigger = MaxPower { size: 4, ..strong };
+        assert!(strong > bigger);
+        let right = MaxPower { x: 2, ..strong };
+        assert!(strong > right);
+        let lower = MaxPower { y: 2, ..strong };
+        assert!(strong > lower);
+    }
+
+    #[test]
+    fn test_max_any_power() {
+        assert_eq!(
+            Grid(18).max_up_to_power(16),
+            MaxPower {
+                x: 90,
+                y: 269,
+                power: 113,
+                size: 16,
+            }
+        );
+        assert_eq!(
+            Grid(42).max_up_to_power(12),
+            MaxPower {
+                x: 232,
+                y: 251,
+                power: 119,
+                size: 12,
+            }
+        );
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_max_up_to_power_par() {
+        assert_eq!(
+            Grid(42).max_up_to_power_par(12),
+            Grid(42).max_up_to_power_seq(12)
+        );
+        assert_eq!(
+            Grid(18).max_up_to_power_par(16),
+            Grid(18).max_up_to_power_seq(16)
+        );
+    }
+}
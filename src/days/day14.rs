@@ -0,0 +1,289 @@
+use std::collections::VecDeque;
+
+// Scores are single digits, so a byte is plenty; the real input needs tens of
+// millions of them.
+pub type Recipe = u8;
+
+pub struct Recipes {
+    // The index of the current recipe of each elf
+    elves: Vec<usize>,
+    recipes: Vec<Recipe>,
+    // The index of the next recipe to yield when used as an iterator
+    yielded: usize,
+}
+
+impl Recipes {
+    pub fn new(first: Recipe, second: Recipe) -> Self {
+        Recipes::with_elves(&[first, second], &[0, 1])
+    }
+
+    /// Start with the given scoreboard, and an elf at each of the given positions on it.
+    pub fn with_elves(initial: &[Recipe], positions: &[usize]) -> Self {
+        assert!(!positions.is_empty(), "Need at least one elf");
+        for &p in positions {
+            assert!(p < initial.len(), "Elf at {} has no recipe", p);
+        }
+
+        Recipes {
+            elves: positions.to_vec(),
+            recipes: initial.to_vec(),
+            yielded: 0,
+        }
+    }
+
+    /// Make new recipes from the digits of the elves' current scores, and move
+    /// every elf forward. Returns the number of recipes added.
+    fn step(&mut self) -> usize {
+        let sum: usize = self.elves.iter().map(|&e| self.recipes[e] as usize).sum();
+        let digits = digits(sum);
+        self.recipes.extend_from_slice(&digits);
+
+        let len = self.recipes.len();
+        for e in self.elves.iter_mut() {
+            *e = (*e + self.recipes[*e] as usize + 1) % len;
+        }
+
+        digits.len()
+    }
+
+    /// The `len` recipes starting at `start_ix`, as a string of digits.
+    pub fn get_string(&mut self, start_ix: usize, len: usize) -> String {
+        self.advance_to(start_ix + len);
+
+        let mut s = String::with_capacity(10);
+        for r in self.recipes.iter().skip(start_ix).take(len) {
+            s.push_str(&r.to_string());
+        }
+
+        s
+    }
+
+    fn advance_to(&mut self, len: usize) {
+        while self.recipes.len() < len {
+            self.step();
+        }
+    }
+
+    fn check_match(&self, needle: &[Recipe], ix: usize) -> bool {
+        self.recipes.get(ix..ix + needle.len()) == Some(needle)
+    }
+
+    pub fn find_set(&mut self, needle: &[Recipe]) -> usize {
+        let n_len = needle.len();
+        if n_len == 0 {
+            return 0;
+        }
+
+        if let Some(ix) = self.recipes.windows(n_len).position(|w| w == needle) {
+            return ix;
+        }
+
+        // Only windows ending in one of the new recipes can be new matches
+        loop {
+            let added = self.step();
+            let len = self.recipes.len();
+            let first_new = (len - added).saturating_sub(n_len - 1);
+            for ix in first_new..=len.saturating_sub(n_len) {
+                if self.check_match(needle, ix) {
+                    return ix;
+                }
+            }
+        }
+    }
+}
+
+/// Every recipe in order, starting from the first, making more as needed.
+///
+/// This doesn't save any memory over `find_set`. Each elf moves forward modulo the number of
+/// recipes, so every so often it wraps round and walks through the earliest recipes again, and
+/// all of them have to be kept. It just lets a consumer like `find_streaming` look at the
+/// recipes one at a time.
+impl Iterator for Recipes {
+    type Item = Recipe;
+
+    fn next(&mut self) -> Option<Recipe> {
+        self.advance_to(self.yielded + 1);
+        let r = self.recipes[self.yielded];
+        self.yielded += 1;
+        Some(r)
+    }
+}
+
+/// The index of the first occurrence of `needle` in `recipes`. The search itself keeps only the
+/// last `needle.len()` recipes seen, though a `Recipes` source still keeps all of its own.
+pub fn find_streaming<I>(recipes: I, needle: &[Recipe]) -> Option<usize>
+where
+    I: IntoIterator<Item = Recipe>,
+{
+    if needle.is_empty() {
+        return Some(0);
+    }
+
+    let mut window: VecDeque<Recipe> = VecDeque::with_capacity(needle.len());
+    for (ix, r) in recipes.into_iter().enumerate() {
+        if window.len() == needle.len() {
+            window.pop_front();
+        }
+        window.push_back(r);
+        if window.len() == needle.len() && window.iter().eq(needle.iter()) {
+            return Some(ix + 1 - needle.len());
+        }
+    }
+
+    None
+}
+
+impl std::fmt::Display for Recipes {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for (n, r) in self.recipes.iter().enumerate() {
+            match self.elves.iter().position(|&e| e == n) {
+                Some(0) => write!(f, "({})", r),
+                Some(_) => write!(f, "[{}]", r),
+                None => write!(f, " {} ", r),
+            }?
+        }
+
+        Ok(())
+    }
+}
+
+/// The decimal digits of `n`, most significant first.
+pub fn digits(n: usize) -> Vec<Recipe> {
+    if n < 10 {
+        return vec![n as Recipe];
+    }
+    let mut ds = digits(n / 10);
+    ds.push((n % 10) as Recipe);
+    ds
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recipes() {
+        let mut r = Recipes::new(3, 7);
+
+        r.advance_to(20);
+        println!("{}", r);
+        assert_eq!(r.get_string(5, 10), "0124515891");
+        assert_eq!(r.get_string(9, 10), "5158916779");
+        assert_eq!(r.get_string(18, 10), "9251071085");
+        assert_eq!(r.get_string(2018, 10), "5941429882");
+    }
+
+    #[test]
+    fn test_digits() {
+        assert_eq!(digits(51589), vec![5, 1, 5, 8, 9]);
+        assert_eq!(digits(0), vec![0]);
+        assert_eq!(digits(10), vec![1, 0]);
+        assert_eq!(digits(939_601), vec![9, 3, 9, 6, 0, 1]);
+
+        let mut r = Recipes::new(3, 7);
+        assert_eq!(r.find_set(&digits(51589)), 9);
+    }
+
+    #[test]
+    fn test_with_elves() {
+        let mut r = Recipes::with_elves(&[3, 7], &[0, 1]);
+        assert_eq!(r.get_string(9, 10), "5158916779");
+
+        // Three elves: 3 + 7 + 1 = 11, after which they all land on index 4
+        let mut r = Recipes::with_elves(&[3, 7, 1], &[0, 1, 2]);
+        assert_eq!(r.step(), 2);
+        assert_eq!(r.recipes, vec![3, 7, 1, 1, 1]);
+        assert_eq!(r.elves, vec![4, 4, 4]);
+        assert_eq!(format!("{}", r), " 3  7  1  1 (1)");
+        // 1 + 1 + 1 = 3
+        assert_eq!(r.step(), 1);
+        assert_eq!(r.recipes, vec![3, 7, 1, 1, 1, 3]);
+        assert_eq!(r.elves, vec![0, 0, 0]);
+    }
+
+    #[test]
+    fn test_find() {
+        let mut r = Recipes::new(3, 7);
+        r.advance_to(20);
+
+        assert_eq!(r.find_set(&[0, 1, 2, 4, 5]), 5);
+        assert_eq!(r.find_set(&[5, 1, 5, 8, 9]), 9);
+        assert_eq!(r.find_set(&[9, 2, 5, 1, 0]), 18);
+        assert_eq!(r.find_set(&[5, 9, 4, 1, 4]), 2018);
+    }
+
+    #[test]
+    fn test_find_matches_scan() {
+        let mut full = Recipes::new(3, 7);
+        full.advance_to(100_000);
+
+        let needles: [&[Recipe]; 6] = [
+            &[3, 7],
+            &[0, 1, 2, 4, 5],
+            &[5, 1, 5, 8, 9],
+            &[9, 2, 5, 1, 0],
+            &[5, 9, 4, 1, 4],
+            &[9, 3, 9, 6],
+        ];
+        for needle in needles.iter() {
+            let expected = full
+                .recipes
+                .windows(needle.len())
+                .position(|w| &w == needle)
+                .unwrap();
+
+            // Starting from scratch, so the match is only found while stepping
+            let mut r = Recipes::new(3, 7);
+            assert_eq!(r.find_set(needle), expected);
+        }
+    }
+
+    #[test]
+    fn test_iterator() {
+        let first: Vec<Recipe> = Recipes::new(3, 7).take(12).collect();
+        assert_eq!(first, vec![3, 7, 1, 0, 1, 0, 1, 2, 4, 5, 1, 5]);
+
+        let mut r = Recipes::new(3, 7);
+        r.advance_to(20);
+        assert_eq!(r.nth(5), Some(0));
+        assert_eq!(r.next(), Some(1));
+    }
+
+    #[test]
+    fn test_find_streaming() {
+        assert_eq!(
+            find_streaming(Recipes::new(3, 7), &[5, 9, 4, 1, 4]),
+            Some(2018)
+        );
+        assert_eq!(find_streaming(Recipes::new(3, 7), &[]), Some(0));
+        assert_eq!(find_streaming(vec![1, 2, 3], &[2, 3, 4]), None);
+
+        let needles: [&[Recipe]; 5] = [
+            &[3, 7],
+            &[0, 1, 2, 4, 5],
+            &[5, 1, 5, 8, 9],
+            &[9, 2, 5, 1, 0],
+            &[9, 3, 9, 6],
+        ];
+        for needle in needles.iter() {
+            let mut r = Recipes::new(3, 7);
+            assert_eq!(
+                find_streaming(Recipes::new(3, 7), needle),
+                Some(r.find_set(needle))
+            );
+        }
+    }
+
+    #[test]
+    fn test_elves_wrap() {
+        // Why Recipes can't forget old recipes: the elves keep coming back to the start
+        let mut r = Recipes::new(3, 7);
+        let mut wraps = 0;
+        while r.recipes.len() < 10_000 {
+            let before = r.elves.clone();
+            r.step();
+            wraps += before.iter().zip(&r.elves).filter(|(b, a)| a < b).count();
+        }
+        assert!(wraps > 10, "Only wrapped {} times", wraps);
+    }
+}
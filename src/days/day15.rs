@@ -0,0 +1,1198 @@
+use crate::grid::neighbors4;
+use crate::pathfind::astar;
+use log::info;
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+
+// Location in the format (y, x) so that they sort naturally into reading order
+type Location = (i16, i16);
+
+trait Distancer<M> {
+    fn dist(self, other: Self) -> M;
+}
+
+impl Distancer<i16> for Location {
+    fn dist(self, other: Self) -> i16 {
+        (self.0 - other.0).abs() + (self.1 - other.1).abs()
+    }
+}
+
+// Each faction is marked by its own letter on the map; elves and goblins are the usual ones.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, PartialOrd, Eq, Ord)]
+pub enum Side {
+    Elf,
+    Goblin,
+    Other(char),
+}
+
+impl Side {
+    /// The faction marked by the given letter on the map, if it's a faction at all.
+    pub fn from_char(c: char) -> Option<Side> {
+        match c {
+            'E' => Some(Side::Elf),
+            'G' => Some(Side::Goblin),
+            c if c.is_ascii_uppercase() => Some(Side::Other(c)),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Hash, PartialEq, PartialOrd, Eq, Ord)]
+struct Character {
+    location: Location,
+    hp: i64,
+    side: Side,
+}
+
+impl Character {
+    fn new(location: Location, hp: i64, side: Side) -> Self {
+        Character { location, hp, side }
+    }
+}
+
+/// Something that happened during a round of combat. Rounds are numbered from
+/// 1, and the last (incomplete) round is included.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RoundEvent {
+    Move {
+        round: usize,
+        side: Side,
+        from: Location,
+        to: Location,
+    },
+    Attack {
+        round: usize,
+        side: Side,
+        attacker: Location,
+        target: Location,
+        damage: i64,
+        killed: bool,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Battle {
+    squares: HashSet<Location>,
+    occupied: HashSet<Location>,
+    characters: Vec<Character>,
+    elf_power: i64,
+    goblin_power: i64,
+    // Attack power for any faction other than elves and goblins
+    other_power: i64,
+    side_counts: HashMap<Side, usize>,
+    // Pairs of factions that are allied (true) or hostile (false). Factions are hostile to
+    // each other unless specified otherwise.
+    alliances: HashMap<(Side, Side), bool>,
+}
+
+/// Parse a map with the puzzle's defaults: 200 hit points, and an attack power of 3 all round.
+/// A blank first line is skipped, so a map can start on the line after the opening quote.
+impl FromStr for Battle {
+    type Err = failure::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lines = s.split('\n').peekable();
+        if lines.peek().map(|l| l.trim().is_empty()) == Some(true) {
+            lines.next();
+        }
+        Battle::parse_lines(lines.map(Ok::<_, failure::Error>), 200, 3, 3)
+    }
+}
+
+impl Battle {
+    pub fn parse_lines<S, E, T>(
+        iter: T,
+        start_hp: i64,
+        elf_power: i64,
+        goblin_power: i64,
+    ) -> Result<Self, failure::Error>
+    where
+        S: AsRef<str>,
+        E: Into<failure::Error>,
+        T: IntoIterator<Item = Result<S, E>>,
+    {
+        let mut squares = HashSet::new();
+        let mut occupied = HashSet::new();
+        let mut characters = Vec::new();
+
+        for (y, l) in iter.into_iter().enumerate() {
+            let line_ref = l.map_err(Into::into)?;
+            let line = line_ref.as_ref();
+            for (x, c) in line.chars().enumerate() {
+                let side = match c {
+                    '#' => continue,
+                    '.' => None,
+                    c => match Side::from_char(c) {
+                        Some(s) => Some(s),
+                        None => panic!("Character {} Not Recognized", c),
+                    },
+                };
+
+                let loc = (y as i16, x as i16);
+                squares.insert(loc);
+                if let Some(s) = side {
+                    characters.push(Character::new(loc, start_hp, s));
+                    occupied.insert(loc);
+                }
+
+                // println!("Found {:?} at ({},{})", side, x, y);
+            }
+        }
+
+        let mut side_counts = HashMap::new();
+        for c in &characters {
+            let e = side_counts.entry(c.side).or_default();
+            *e += 1;
+        }
+
+        Ok(Battle {
+            squares,
+            characters,
+            occupied,
+            elf_power,
+            goblin_power,
+            // Other factions start with the same base power as goblins
+            other_power: goblin_power,
+            side_counts,
+            alliances: HashMap::new(),
+        })
+    }
+
+    /// Set whether two factions are allied.
+    pub fn set_allied(&mut self, a: Side, b: Side, allied: bool) {
+        self.alliances.insert((a, b), allied);
+        self.alliances.insert((b, a), allied);
+    }
+
+    /// Set the attack power of factions other than elves and goblins.
+    pub fn set_other_power(&mut self, power: i64) {
+        self.other_power = power;
+    }
+
+    fn hostile(&self, a: Side, b: Side) -> bool {
+        a != b && !self.alliances.get(&(a, b)).cloned().unwrap_or(false)
+    }
+
+    fn empty_neighbors(&self, loc: Location, allow: Option<Location>) -> Vec<Location> {
+        // In reading order
+        let mut locs: Vec<Location> = neighbors4(loc).to_vec();
+        // Keep neighbors that are (in allow) or (are viable squares and unoccupied)
+        locs.retain(|&loc| {
+            allow.map(|l| l == loc).unwrap_or(false)
+                || (self.squares.contains(&loc) && !self.occupied.contains(&loc))
+        });
+        locs
+    }
+
+    // Returns (next step, goal, enemies_found)
+    fn find_target(&self, character: Character) -> Option<(Location, Location, bool)> {
+        // Empty squares next to an enemy, each with the first such enemy in reading order
+        let mut goals: HashMap<Location, Location> = HashMap::new();
+        let mut enemies_found = 0;
+        for target in &self.characters {
+            if !self.hostile(target.side, character.side) || target.hp <= 0 {
+                continue;
+            }
+            enemies_found += 1;
+
+            for empty in self.empty_neighbors(target.location, Some(character.location)) {
+                let first = goals.entry(empty).or_insert(target.location);
+                *first = (*first).min(target.location);
+            }
+        }
+
+        if enemies_found == 0 {
+            return Some((character.location, character.location, false));
+        }
+
+        // Nodes are (location, first step), so the nearest goal in reading order is found
+        // first, and reached by the first step in reading order
+        let start = character.location;
+        let (_, path) = astar(
+            (start, start),
+            |&(loc, _)| goals.contains_key(&loc),
+            |&(loc, step)| {
+                self.empty_neighbors(loc, None).into_iter().map(move |n| {
+                    let first = if loc == start { n } else { step };
+                    ((n, first), 1)
+                })
+            },
+            |_| 0i16,
+        )?;
+
+        let &(goal, step) = path.last()?;
+        Some((step, goals[&goal], true))
+    }
+
+    fn target_to_attack(&mut self, c: Character) -> Option<&mut Character> {
+        let mut target: Option<usize> = None;
+        for (ix, t) in self.characters.iter().enumerate() {
+            if !self.hostile(t.side, c.side) || t.hp <= 0 || c.location.dist(t.location) != 1 {
+                continue;
+            }
+            match target {
+                Some(m) if self.characters[m].hp <= t.hp => {}
+                _ => target = Some(ix),
+            };
+        }
+
+        target.map(move |ix| &mut self.characters[ix])
+    }
+
+    fn attack_power(&self, character: Character) -> i64 {
+        match character.side {
+            Side::Goblin => self.goblin_power,
+            Side::Elf => self.elf_power,
+            Side::Other(_) => self.other_power,
+        }
+    }
+
+    #[cfg(test)]
+    fn round(&mut self) -> bool {
+        self.round_logged(0, &mut Vec::new())
+    }
+
+    /// Run a single round, adding what happened to `events`.
+    ///
+    /// Characters take turns in reading order as of the start of the round, each at most once,
+    /// even if they move past others. A character killed earlier in the round gets no turn, and
+    /// its square is free for the rest of the round.
+    fn round_logged(&mut self, round: usize, events: &mut Vec<RoundEvent>) -> bool {
+        for ix in 0..self.characters.len() {
+            let mut c = self.characters[ix];
+            if c.hp <= 0 {
+                // Dead characters don't move
+                continue;
+            }
+            let (step, _goal, any_enemies) = match self.find_target(c) {
+                None => {
+                    // println!("Can't move {:?} at {:?}", c.side, c.location);
+                    // This character can't reach any enemies.
+                    // No moving or attacking.
+                    continue;
+                }
+                Some(st) => st,
+            };
+            if !any_enemies {
+                return false;
+            }
+            // Move. This may be a no-op if we're already next to a target.
+            if c.location != step {
+                events.push(RoundEvent::Move {
+                    round,
+                    side: c.side,
+                    from: c.location,
+                    to: step,
+                });
+                self.occupied.remove(&c.location);
+                c.location = step;
+                self.occupied.insert(c.location);
+            }
+            self.characters[ix] = c;
+
+            // Attack.
+            let ap = self.attack_power(c);
+            let to_remove = if let Some(t) = self.target_to_attack(c) {
+                t.hp -= ap;
+                events.push(RoundEvent::Attack {
+                    round,
+                    side: c.side,
+                    attacker: c.location,
+                    target: t.location,
+                    damage: ap,
+                    killed: t.hp <= 0,
+                });
+                if t.hp <= 0 {
+                    Some(t.location)
+                } else {
+                    None
+                }
+            } else {
+                None
+            };
+
+            // Mark spots of dead characters as unoccupied.
+            if let Some(loc) = to_remove {
+                self.occupied.remove(&loc);
+            }
+        }
+
+        self.characters.retain(|c| c.hp > 0);
+        self.occupied.clear();
+        for c in &self.characters {
+            if c.hp > 0 {
+                self.occupied.insert(c.location);
+            }
+        }
+        self.characters.sort();
+
+        true
+    }
+
+    /// Run to completion. Returns (# of rounds, total hp, side that won). If multiple allied
+    /// sides won, the side returned is that of the last surviving character in reading order.
+    pub fn complete(&mut self) -> (usize, i64, Side) {
+        let (n, hp, side, _) = self.complete_logged();
+        (n, hp, side)
+    }
+
+    /// Run to completion. Returns (# of rounds, total hp, side that won, events)
+    pub fn complete_logged(&mut self) -> (usize, i64, Side, Vec<RoundEvent>) {
+        let mut events = Vec::new();
+        let mut n = 0;
+        while self.round_logged(n + 1, &mut events) {
+            n += 1;
+        }
+
+        let mut side = Side::Elf;
+        let mut hp = 0;
+        for c in &self.characters {
+            if c.hp <= 0 {
+                continue;
+            }
+            side = c.side;
+            hp += c.hp;
+        }
+
+        (n, hp, side, events)
+    }
+
+    fn deaths(&self, side: Side) -> usize {
+        // self.characters
+        //     .iter()
+        //     .filter(|c| c.side == side && c.hp <= 0)
+        //     .count()
+
+        let mut deaths = self.side_counts[&side];
+        for c in &self.characters {
+            if c.side != side || c.hp < 0 {
+                continue;
+            }
+            deaths -= 1;
+        }
+
+        deaths
+    }
+
+    // Run to completion at the given elf power, logging a summary. Returns the battle at its
+    // end, and the (# of rounds, total hp) it ended with.
+    fn try_elf_power(&self, elf_power: i64) -> (Battle, usize, i64) {
+        let mut battle = self.clone();
+        battle.elf_power = elf_power;
+        let (rounds, hp, side) = battle.complete();
+        let elf_deaths = battle.deaths(Side::Elf);
+        info!(
+            "{:?} win with {} hp and {} elves died after {} rounds at elf power {}.",
+            side, hp, elf_deaths, rounds, elf_power
+        );
+
+        (battle, rounds, hp)
+    }
+
+    /// Run to completion with the smallest elf power at which no elves die.
+    /// Returns (# of rounds, total hp, elf power)
+    pub fn save_the_elves(&mut self) -> (usize, i64, i64) {
+        let mut low = self.elf_power;
+        let (mut best, mut rounds, mut hp) = self.try_elf_power(low);
+        if best.deaths(Side::Elf) == 0 {
+            *self = best;
+            return (rounds, hp, low);
+        }
+
+        // Saving the elves gets easier with more power, so we find a power
+        // that works by doubling, and then bisect between that and the
+        // highest known failure.
+        let mut high = low;
+        loop {
+            high = (high * 2).max(high + 1);
+            let (battle, r, h) = self.try_elf_power(high);
+            if battle.deaths(Side::Elf) == 0 {
+                best = battle;
+                rounds = r;
+                hp = h;
+                break;
+            }
+            low = high;
+        }
+
+        while high - low > 1 {
+            let mid = (low + high) / 2;
+            let (battle, r, h) = self.try_elf_power(mid);
+            if battle.deaths(Side::Elf) == 0 {
+                best = battle;
+                rounds = r;
+                hp = h;
+                high = mid;
+            } else {
+                low = mid;
+            }
+        }
+
+        *self = best;
+        (rounds, hp, high)
+    }
+
+    // The same as save_the_elves, but checks every elf power in turn.
+    #[cfg(test)]
+    fn save_the_elves_linear(&mut self) -> (usize, i64, i64) {
+        let mut elf_power = self.elf_power;
+        loop {
+            let (battle, rounds, hp) = self.try_elf_power(elf_power);
+            if battle.deaths(Side::Elf) == 0 {
+                *self = battle;
+                return (rounds, hp, elf_power);
+            }
+            elf_power += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_test_battle(s: &str) -> Battle {
+        Battle::from_str(s).unwrap()
+    }
+
+    fn get_test_battle_with_powers(s: &str, elf_power: i64, goblin_power: i64) -> Battle {
+        let mut battle = get_test_battle(s);
+        battle.elf_power = elf_power;
+        battle.goblin_power = goblin_power;
+        battle
+    }
+
+    fn get_characters(battle: &Battle) -> Vec<Character> {
+        battle
+            .characters
+            .iter()
+            .filter_map(|&c| if c.hp > 0 { Some(c) } else { None })
+            .collect()
+    }
+
+    #[test]
+    fn test_targeting() {
+        let test_input = r#"
+#######
+#E..G.#
+#...#.#
+#.G.#G#
+#######"#;
+
+        let battle = get_test_battle(test_input);
+
+        assert_eq!(battle.characters.len(), 4);
+        assert_eq!(battle.occupied.len(), battle.characters.len());
+        assert_eq!(battle.squares.len(), 13);
+
+        let &c = battle.characters.first().unwrap();
+        assert_eq!(c.location, (1, 1));
+        let (s, g, _) = battle.find_target(c).unwrap();
+        assert_eq!(s, (1, 2));
+        assert_eq!(g, (1, 4));
+    }
+
+    #[test]
+    fn test_far_targeting() {
+        let test_input = r#"
+#######
+#.....#
+#..E..#
+#.....#
+#..####
+#.....#
+#..##.#
+#####.#
+#...G.#
+#######"#;
+
+        let battle = get_test_battle(test_input);
+        assert_eq!(battle.characters.len(), 2);
+        assert_eq!(battle.occupied.len(), battle.characters.len());
+
+        let &c = battle.characters.first().unwrap();
+        assert_eq!(c.location, (2, 3));
+        let (s, g, _) = battle.find_target(c).unwrap();
+        assert_eq!(s, (2, 2));
+        assert_eq!(g, (8, 4));
+    }
+
+    #[test]
+    fn test_blocked_targeting() {
+        let test_input = r#"
+#######
+#.E...#
+#..##.#
+#E##..#
+#G....#
+#######"#;
+
+        let battle = get_test_battle(test_input);
+        assert_eq!(battle.characters.len(), 3);
+        assert_eq!(battle.occupied.len(), battle.characters.len());
+        assert_eq!(battle.squares.len(), 16);
+
+        let &c = battle.characters.first().unwrap();
+        assert_eq!(c.location, (1, 2));
+        let (s, g, _) = battle.find_target(c).unwrap();
+        assert_eq!(s, (1, 3));
+        assert_eq!(g, (4, 1));
+    }
+
+    #[test]
+    fn test_near_targeting() {
+        let test_input = r#"
+#######
+#.EG..#
+#..G..#
+#..#..#
+#G....#
+#######"#;
+
+        let battle = get_test_battle(test_input);
+        assert_eq!(battle.characters.len(), 4);
+        assert_eq!(battle.occupied.len(), battle.characters.len());
+        assert_eq!(battle.squares.len(), 19);
+
+        let &c = battle.characters.first().unwrap();
+        assert_eq!(c.location, (1, 2));
+        let (s, g, _) = battle.find_target(c).unwrap();
+        assert_eq!(s, (1, 2));
+        assert_eq!(g, (1, 3));
+    }
+
+    #[test]
+    fn test_shared_astar() {
+        let test_input = r#"
+#######
+#.....#
+#..E..#
+#.....#
+#..####
+#.....#
+#..##.#
+#####.#
+#...G.#
+#######"#;
+
+        let battle = get_test_battle(test_input);
+        let &c = battle.characters.first().unwrap();
+        // The goblin can only be reached from the right; the squares to its left are cut off
+        let goals: HashSet<Location> = [(8, 3), (8, 5)].iter().cloned().collect();
+
+        let start = (c.location, c.location);
+        let (dist, path) = astar(
+            start,
+            |&(loc, _)| goals.contains(&loc),
+            |&(loc, step)| {
+                battle.empty_neighbors(loc, None).into_iter().map(move |n| {
+                    let first = if loc == c.location { n } else { step };
+                    ((n, first), 1)
+                })
+            },
+            |_| 0i16,
+        )
+        .unwrap();
+
+        assert_eq!(dist, 10);
+        assert_eq!(path.len(), 11);
+        assert_eq!(path.first(), Some(&start));
+        assert_eq!(path.last(), Some(&((8, 5), (2, 2))));
+        assert_eq!(battle.find_target(c), Some(((2, 2), (8, 4), true)));
+    }
+
+    fn get_test_battle_with_hps(s: &str, hps: &[i64]) -> Battle {
+        let mut battle = get_test_battle(s);
+        assert_eq!(battle.characters.len(), hps.len());
+        for (c, &hp) in battle.characters.iter_mut().zip(hps) {
+            c.hp = hp;
+        }
+
+        battle
+    }
+
+    #[test]
+    fn test_rounds() {
+        let initial = r"
+#######
+#.G...#
+#...EG#
+#.#.#G#
+#..G#E#
+#.....#
+#######";
+
+        let round1_str = r"
+#######
+#..G..#
+#...EG#
+#.#G#G#
+#...#E#
+#.....#
+#######";
+
+        let mut battle = get_test_battle(initial);
+        println!("Running round 1");
+        battle.round();
+        let chars = get_characters(&battle);
+
+        let expected_hps = &[200i64, 197, 197, 200, 197, 197];
+        let round1 = get_test_battle_with_hps(round1_str, expected_hps);
+        let exp_chars = get_characters(&round1);
+        assert_eq!(chars, exp_chars);
+
+        let round2_str = r"
+#######
+#...G.#
+#..GEG#
+#.#.#G#
+#...#E#
+#.....#
+#######";
+
+        println!("Running round 2");
+        battle.round();
+        let chars = get_characters(&battle);
+        let expected_hps = &[200i64, 200, 188, 194, 194, 194];
+        let round2 = get_test_battle_with_hps(round2_str, expected_hps);
+        let exp_chars = get_characters(&round2);
+        assert_eq!(chars, exp_chars);
+
+        let round23_str = r"
+#######
+#...G.#
+#..G.G#
+#.#.#G#
+#...#E#
+#.....#
+#######";
+        let expected_hps = &[200i64, 200, 131, 131, 131];
+        let round23 = get_test_battle_with_hps(round23_str, expected_hps);
+        let exp_chars = get_characters(&round23);
+
+        for n in 2..23 {
+            println!("Running round {}", n + 1);
+            battle.round();
+        }
+
+        let chars = get_characters(&battle);
+
+        assert_eq!(chars, exp_chars);
+
+        let round24_str = r"
+#######
+#..G..#
+#...G.#
+#.#G#G#
+#...#E#
+#.....#
+#######";
+        let expected_hps = &[200i64, 131, 200, 128, 128];
+        let round24 = get_test_battle_with_hps(round24_str, expected_hps);
+        let exp_chars = get_characters(&round24);
+
+        for n in 23..24 {
+            println!("Running round {}", n + 1);
+            battle.round();
+        }
+
+        let chars = get_characters(&battle);
+
+        assert_eq!(chars, exp_chars);
+
+        let round25_str = r"
+#######
+#.G...#
+#..G..#
+#.#.#G#
+#..G#E#
+#.....#
+#######";
+        let expected_hps = &[200i64, 131, 125, 200, 125];
+        let round25 = get_test_battle_with_hps(round25_str, expected_hps);
+        let exp_chars = get_characters(&round25);
+
+        for n in 24..25 {
+            println!("Running round {}", n + 1);
+            battle.round();
+        }
+
+        let chars = get_characters(&battle);
+
+        assert_eq!(chars, exp_chars);
+
+        let round47_str = r"
+#######
+#G....#
+#.G...#
+#.#.#G#
+#...#.#
+#....G#
+#######";
+
+        let expected_hps = &[200i64, 131, 59, 200];
+        let round47 = get_test_battle_with_hps(round47_str, expected_hps);
+        let exp_chars = get_characters(&round47);
+
+        for n in 25..47 {
+            println!("Running round {}", n + 1);
+            let finished = battle.round();
+            assert!(finished);
+        }
+
+        let chars = get_characters(&battle);
+
+        assert_eq!(chars, exp_chars);
+
+        println!("Running round 48");
+        let finished = battle.round();
+        assert!(!finished);
+    }
+
+    #[test]
+    fn test_mid_round_deaths() {
+        // The elf kills the weak goblin to its right before that goblin's turn, opening up a
+        // path for the goblin further right
+        let map = r"
+#######
+#GEG.G#
+#######";
+
+        let mut battle = get_test_battle_with_hps(map, &[200, 200, 3, 200]);
+        let mut events = Vec::new();
+        assert!(battle.round_logged(1, &mut events));
+        assert_eq!(
+            events,
+            vec![
+                RoundEvent::Attack {
+                    round: 1,
+                    side: Side::Goblin,
+                    attacker: (1, 1),
+                    target: (1, 2),
+                    damage: 3,
+                    killed: false,
+                },
+                RoundEvent::Attack {
+                    round: 1,
+                    side: Side::Elf,
+                    attacker: (1, 2),
+                    target: (1, 3),
+                    damage: 3,
+                    killed: true,
+                },
+                RoundEvent::Move {
+                    round: 1,
+                    side: Side::Goblin,
+                    from: (1, 5),
+                    to: (1, 4),
+                },
+            ]
+        );
+        assert_eq!(
+            get_characters(&battle),
+            vec![
+                Character::new((1, 1), 200, Side::Goblin),
+                Character::new((1, 2), 197, Side::Elf),
+                Character::new((1, 4), 200, Side::Goblin),
+            ]
+        );
+
+        // With no death, the middle goblin gets its turn, and the last goblin is stuck
+        let mut battle = get_test_battle(map);
+        let mut events = Vec::new();
+        assert!(battle.round_logged(1, &mut events));
+        let attackers: Vec<Location> = events
+            .iter()
+            .map(|e| match *e {
+                RoundEvent::Attack { attacker, .. } => attacker,
+                RoundEvent::Move { .. } => panic!("Unexpected move: {:?}", e),
+            })
+            .collect();
+        assert_eq!(attackers, vec![(1, 1), (1, 2), (1, 3)]);
+
+        // Killing the last enemy ends combat, without the dead goblin getting a turn
+        let mut battle = get_test_battle_with_hps("\n#####\n#EGE#\n#####", &[200, 3, 200]);
+        let mut events = Vec::new();
+        assert!(!battle.round_logged(1, &mut events));
+        assert_eq!(events.len(), 1);
+        assert_eq!(
+            get_characters(&battle),
+            vec![
+                Character::new((1, 1), 200, Side::Elf),
+                Character::new((1, 3), 200, Side::Elf),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_completion() {
+        let initial = r"
+#######
+#.G...#
+#...EG#
+#.#.#G#
+#..G#E#
+#.....#
+#######";
+
+        let mut battle = get_test_battle(initial);
+        let (rounds, hp, side) = battle.complete();
+
+        assert_eq!(rounds, 47);
+        assert_eq!(hp, 590);
+        assert_eq!(side, Side::Goblin);
+    }
+
+    #[test]
+    fn test_complete_logged() {
+        let initial = r"
+#######
+#.G...#
+#...EG#
+#.#.#G#
+#..G#E#
+#.....#
+#######";
+
+        let start = get_test_battle(initial);
+        let mut battle = start.clone();
+        let (rounds, hp, side, events) = battle.complete_logged();
+        assert_eq!((rounds, hp, side), (47, 590, Side::Goblin));
+        assert_eq!(start.clone().complete(), (rounds, hp, side));
+
+        let last_attack = events
+            .iter()
+            .rev()
+            .find(|e| match e {
+                RoundEvent::Attack { .. } => true,
+                RoundEvent::Move { .. } => false,
+            })
+            .unwrap();
+        match *last_attack {
+            RoundEvent::Attack {
+                round,
+                side,
+                damage,
+                killed,
+                ..
+            } => {
+                assert_eq!(round, 47);
+                assert_eq!(side, Side::Goblin);
+                assert_eq!(damage, 3);
+                assert!(killed);
+            }
+            RoundEvent::Move { .. } => unreachable!(),
+        }
+
+        // Replaying the log from the start should give the final positions
+        let mut positions: HashMap<Location, Side> = start
+            .characters
+            .iter()
+            .map(|c| (c.location, c.side))
+            .collect();
+        for e in &events {
+            match *e {
+                RoundEvent::Move { side, from, to, .. } => {
+                    assert_eq!(positions.remove(&from), Some(side));
+                    assert_eq!(positions.insert(to, side), None);
+                }
+                RoundEvent::Attack { target, killed, .. } => {
+                    assert!(positions.contains_key(&target));
+                    if killed {
+                        positions.remove(&target);
+                    }
+                }
+            }
+        }
+
+        let mut replayed: Vec<(Location, Side)> = positions.into_iter().collect();
+        replayed.sort();
+        let expected: Vec<(Location, Side)> = get_characters(&battle)
+            .iter()
+            .map(|c| (c.location, c.side))
+            .collect();
+        assert_eq!(replayed, expected);
+    }
+
+    #[test]
+    fn test_factions() {
+        let initial = r"
+#######
+#EX..G#
+#######";
+
+        // Returns the (attacker, target) sides of every attack
+        fn attacks(start: &Battle, events: &[RoundEvent]) -> Vec<(Side, Side)> {
+            let mut positions: HashMap<Location, Side> = start
+                .characters
+                .iter()
+                .map(|c| (c.location, c.side))
+                .collect();
+            let mut attacks = Vec::new();
+            for e in events {
+                match *e {
+                    RoundEvent::Move { side, from, to, .. } => {
+                        positions.remove(&from);
+                        positions.insert(to, side);
+                    }
+                    RoundEvent::Attack {
+                        side,
+                        target,
+                        killed,
+                        ..
+                    } => {
+                        attacks.push((side, positions[&target]));
+                        if killed {
+                            positions.remove(&target);
+                        }
+                    }
+                }
+            }
+            attacks
+        }
+
+        let x = Side::Other('X');
+        let start = get_test_battle(initial);
+        assert_eq!(start.characters.len(), 3);
+        assert_eq!(start.characters[1].side, x);
+
+        // Without an alliance, the elf and X fight each other first
+        let mut battle = start.clone();
+        let (_, _, _, events) = battle.complete_logged();
+        let all_attacks = attacks(&start, &events);
+        assert_eq!(all_attacks[0], (Side::Elf, x));
+        assert!(all_attacks.contains(&(x, Side::Elf)));
+
+        // With one, they gang up on the goblin
+        let mut allied = start.clone();
+        allied.set_allied(Side::Elf, x, true);
+        let mut battle = allied.clone();
+        let (_, _, side, events) = battle.complete_logged();
+        assert_ne!(side, Side::Goblin);
+        let all_attacks = attacks(&allied, &events);
+        assert!(!all_attacks.is_empty());
+        for &(a, t) in &all_attacks {
+            assert!(a == Side::Goblin || t == Side::Goblin);
+        }
+        assert_eq!(battle.deaths(Side::Goblin), 1);
+
+        // Other factions hit with their own configured power
+        let mut battle = start.clone();
+        assert_eq!(battle.other_power, 3);
+        battle.other_power = 10;
+        let mut events = Vec::new();
+        battle.round_logged(1, &mut events);
+        let damage: Vec<(Side, i64)> = events
+            .iter()
+            .filter_map(|e| match *e {
+                RoundEvent::Attack { side, damage, .. } => Some((side, damage)),
+                RoundEvent::Move { .. } => None,
+            })
+            .collect();
+        assert_eq!(damage, vec![(Side::Elf, 3), (x, 10)]);
+    }
+
+    #[test]
+    fn test_goblin_power() {
+        let initial = r"
+#######
+#.G...#
+#...EG#
+#.#.#G#
+#..G#E#
+#.....#
+#######";
+
+        let mut battle = get_test_battle_with_powers(initial, 15, 3);
+        let (rounds, hp, side) = battle.complete();
+        assert_eq!((rounds, hp, side), (29, 172, Side::Elf));
+
+        let mut battle = get_test_battle_with_powers(initial, 15, 20);
+        let (rounds, hp, side) = battle.complete();
+        assert_eq!(side, Side::Goblin);
+        assert_ne!((rounds, hp), (29, 172));
+        assert_eq!(battle.deaths(Side::Elf), 2);
+    }
+
+    #[test]
+    fn test_maximization() {
+        let initial = r"
+#######
+#.G...#
+#...EG#
+#.#.#G#
+#..G#E#
+#.....#
+#######";
+
+        let finished = r"
+#######
+#..E..#
+#...E.#
+#.#.#.#
+#...#.#
+#.....#
+#######";
+
+        let mut battle = get_test_battle(initial);
+
+        let (r, hp, power) = battle.save_the_elves();
+        assert_eq!(r, 29);
+        assert_eq!(hp, 172);
+        assert_eq!(power, 15);
+        assert_eq!(r as i64 * hp, 4988);
+        let end_state = get_test_battle_with_hps(finished, &[158, 14]);
+        assert_eq!(get_characters(&battle), get_characters(&end_state));
+
+        let next = r"
+#######
+#E..EG#
+#.#G.E#
+#E.##E#
+#G..#.#
+#..E#.#
+#######";
+
+        let finished = r"
+#######
+#.E.E.#
+#.#E..#
+#E.##E#
+#.E.#.#
+#...#.#
+#######";
+
+        let mut battle = get_test_battle(next);
+
+        let (r, hp, power) = battle.save_the_elves();
+        assert_eq!(r, 33);
+        assert_eq!(hp, 948);
+        assert_eq!(power, 4);
+        assert_eq!(r as i64 * hp, 31284);
+        let end_state = get_test_battle_with_hps(finished, &[200, 23, 200, 125, 200, 200]);
+        assert_eq!(get_characters(&battle), get_characters(&end_state));
+    }
+
+    #[test]
+    fn test_maximization_matches_linear() {
+        let scenarios = [
+            r"
+#######
+#.G...#
+#...EG#
+#.#.#G#
+#..G#E#
+#.....#
+#######",
+            r"
+#######
+#E..EG#
+#.#G.E#
+#E.##E#
+#G..#.#
+#..E#.#
+#######",
+        ];
+
+        for &s in &scenarios {
+            let mut binary = get_test_battle(s);
+            let mut linear = get_test_battle(s);
+            assert_eq!(binary.save_the_elves(), linear.save_the_elves_linear());
+            assert_eq!(get_characters(&binary), get_characters(&linear));
+        }
+
+        // If the elves are already strong enough, nothing changes
+        let mut battle = get_test_battle_with_powers(scenarios[0], 15, 3);
+        let (_, _, power) = battle.save_the_elves();
+        assert_eq!(power, 15);
+    }
+
+    #[test]
+    fn test_more_maximization() {
+        let next = r"
+#######
+#E.G#.#
+#.#G..#
+#G.#.G#
+#G..#.#
+#...E.#
+#######";
+
+        let finished = r"
+#######
+#.E.#.#
+#.#E..#
+#..#..#
+#...#.#
+#.....#
+#######";
+
+        let mut battle = get_test_battle(next);
+        let (r, hp, power) = battle.save_the_elves();
+        assert_eq!(r, 37);
+        assert_eq!(hp, 94);
+        assert_eq!(power, 15);
+        assert_eq!(r as i64 * hp, 3478);
+        let end_state = get_test_battle_with_hps(finished, &[8, 86]);
+        assert_eq!(get_characters(&battle), get_characters(&end_state));
+
+        let next = r"
+#######
+#.E...#
+#.#..G#
+#.###.#
+#E#G#G#
+#...#G#
+#######";
+
+        let finished = r"
+#######
+#...E.#
+#.#..E#
+#.###.#
+#.#.#.#
+#...#.#
+#######
+";
+
+        let mut battle = get_test_battle(next);
+        let (r, hp, power) = battle.save_the_elves();
+        assert_eq!(r, 39);
+        assert_eq!(hp, 166);
+        assert_eq!(power, 12);
+        assert_eq!(r as i64 * hp, 6474);
+        let end_state = get_test_battle_with_hps(finished, &[14, 152]);
+        assert_eq!(get_characters(&battle), get_characters(&end_state));
+
+        let next = r"
+#########
+#G......#
+#.E.#...#
+#..##..G#
+#...##..#
+#...#...#
+#.G...G.#
+#.....G.#
+#########";
+
+        let finished = r"
+#########
+#.......#
+#.E.#...#
+#..##...#
+#...##..#
+#...#...#
+#.......#
+#.......#
+#########";
+
+        let mut battle = get_test_battle(next);
+        let (r, hp, power) = battle.save_the_elves();
+        assert_eq!(r, 30);
+        assert_eq!(hp, 38);
+        assert_eq!(power, 34);
+        assert_eq!(r as i64 * hp, 1140);
+        let end_state = get_test_battle_with_hps(finished, &[38]);
+        assert_eq!(get_characters(&battle), get_characters(&end_state));
+    }
+}
@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+
+fn find_multiples(s: &str) -> (bool, bool) {
+    let mut counts: HashMap<char, i8> = HashMap::new();
+
+    for c in s.chars() {
+        let v = counts.entry(c).or_insert(0);
+        *v += 1;
+    }
+
+    let (mut doubles, mut triples) = (false, false);
+    for &v in counts.values() {
+        match v {
+            3 => triples = true,
+            2 => doubles = true,
+            _ => {}
+        }
+    }
+
+    (doubles, triples)
+}
+
+// The number of positions at which two equal-length IDs differ, or None if the lengths differ.
+fn hamming(a: &str, b: &str) -> Option<usize> {
+    if a.len() != b.len() {
+        return None;
+    }
+    Some(a.chars().zip(b.chars()).filter(|(x, y)| x != y).count())
+}
+
+// The indices (i, j), i < j, of every pair of equal-length IDs at most k characters apart.
+fn within_distance(ids: &[&str], k: usize) -> Vec<(usize, usize)> {
+    let mut pairs = Vec::new();
+    for (i, a) in ids.iter().enumerate() {
+        for (j, b) in ids.iter().enumerate().skip(i + 1) {
+            match hamming(a, b) {
+                Some(d) if d <= k => pairs.push((i, j)),
+                _ => {}
+            }
+        }
+    }
+    pairs
+}
+
+// The indices of the first two IDs that differ by exactly one character.
+fn closest_pair(ids: &[&str]) -> Option<(usize, usize)> {
+    within_distance(ids, 1)
+        .into_iter()
+        .find(|&(i, j)| hamming(ids[i], ids[j]) == Some(1))
+}
+
+// The checksum: the number of IDs with a letter exactly twice, times those with one exactly
+// three times.
+fn checksum(ids: &[&str]) -> usize {
+    let (mut doubles, mut triples) = (0, 0);
+    for id in ids {
+        let (double, triple) = find_multiples(id);
+        if double {
+            doubles += 1
+        }
+        if triple {
+            triples += 1
+        }
+    }
+    doubles * triples
+}
+
+/// The checksum, and the letters in common between the two IDs that differ by one character
+/// (empty if there aren't any).
+pub fn solve(input: &str) -> Result<(String, String), failure::Error> {
+    let ids: Vec<&str> = crate::input::from_str(input).collect();
+    let common: String = match closest_pair(&ids) {
+        Some((i, j)) => ids[i]
+            .chars()
+            .zip(ids[j].chars())
+            .filter_map(|(a, b)| if a == b { Some(a) } else { None })
+            .collect(),
+        None => String::new(),
+    };
+    Ok((checksum(&ids).to_string(), common))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IDS: &[&str] = &[
+        "abcde", "fghij", "klmno", "pqrst", "fguij", "axcye", "wvxyz",
+    ];
+
+    #[test]
+    fn test_closest_pair() {
+        assert_eq!(closest_pair(IDS), Some((1, 4)));
+        assert_eq!(closest_pair(&["abc", "xyz"]), None);
+        // Different lengths are never close
+        assert_eq!(closest_pair(&["abc", "abcd"]), None);
+    }
+
+    #[test]
+    fn test_solve() {
+        let boxes = "abcdef\nbababc\nabbcde\nabcccd\naabcdd\nabcdee\nababab\n";
+        assert_eq!(solve(boxes).unwrap().0, "12");
+        assert_eq!(
+            solve(&IDS.join("\n")).unwrap(),
+            ("0".to_owned(), "fgij".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_within_distance() {
+        assert_eq!(within_distance(IDS, 1), vec![(1, 4)]);
+        // abcde / axcye differ in 2 places
+        assert_eq!(within_distance(IDS, 2), vec![(0, 5), (1, 4)]);
+        assert_eq!(
+            within_distance(IDS, 5).len(),
+            IDS.len() * (IDS.len() - 1) / 2
+        );
+    }
+}
@@ -0,0 +1,141 @@
+use std::str::FromStr;
+
+fn parse_vec<F: FromStr>(s: &str) -> Result<Vec<F>, <F as FromStr>::Err> {
+    let splits = s.trim().split(' ');
+    splits.map(|s| F::from_str(s)).collect()
+}
+
+#[derive(Clone, Debug)]
+struct Parsed {
+    metadata: Vec<i64>,
+    children: Vec<Parsed>,
+}
+
+impl Parsed {
+    fn parse(nums: &[i64]) -> Result<Parsed, failure::Error> {
+        let (p, r) = Parsed::parse_single(nums)?;
+        if !r.is_empty() {
+            failure::bail!("Numbers left over after the root node: {:?}", r);
+        }
+
+        Ok(p)
+    }
+
+    fn parse_single(nums: &[i64]) -> Result<(Parsed, &[i64]), failure::Error> {
+        if nums.len() < 2 {
+            failure::bail!("Input ended before a node's header");
+        }
+        let n_children = nums[0] as usize;
+        let n_metadata = nums[1] as usize;
+
+        let mut remaining = &nums[2..];
+        let mut children = vec![];
+        for _ in 0..n_children {
+            let (child, r) = Parsed::parse_single(remaining)?;
+            children.push(child);
+            remaining = r;
+        }
+
+        if remaining.len() < n_metadata {
+            failure::bail!("Input ended before a node's metadata");
+        }
+        let (metadata, remaining) = remaining.split_at(n_metadata);
+
+        let p = Parsed {
+            children,
+            metadata: metadata.to_vec(),
+        };
+
+        Ok((p, remaining))
+    }
+
+    fn sum_metadata(&self) -> i64 {
+        let child_sum: i64 = self.children.iter().map(|c| c.sum_metadata()).sum();
+
+        let n: i64 = self.metadata.iter().sum::<i64>();
+
+        n + child_sum
+    }
+
+    fn value(&self) -> i64 {
+        if self.children.is_empty() {
+            return self.sum_metadata();
+        }
+
+        // Work out each child's value once, however many times the metadata refers to it, so
+        // every node is only visited once
+        let child_values: Vec<i64> = self.children.iter().map(Parsed::value).collect();
+
+        let mut sum = 0;
+        for &n in &self.metadata {
+            if n < 1 || n > (self.children.len() as i64) {
+                continue;
+            }
+
+            sum += child_values[(n - 1) as usize];
+        }
+
+        sum
+    }
+}
+
+/// The sum of all the metadata, and the value of the root node.
+pub fn solve(input: &str) -> Result<(String, String), failure::Error> {
+    let v: Vec<i64> = parse_vec(input)?;
+    let p = Parsed::parse(&v)?;
+    Ok((p.sum_metadata().to_string(), p.value().to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metadata_sum() {
+        let input = "2 3 0 3 10 11 12 1 1 0 1 99 2 1 1 2";
+        let nums: Vec<i64> = parse_vec(input).unwrap();
+        let p = Parsed::parse(&nums).unwrap();
+
+        assert_eq!(p.sum_metadata(), 138);
+    }
+
+    #[test]
+    fn test_value() {
+        let input = "2 3 0 3 10 11 12 1 1 0 1 99 2 1 1 2";
+        let nums: Vec<i64> = parse_vec(input).unwrap();
+        let p = Parsed::parse(&nums).unwrap();
+
+        assert_eq!(p.value(), 66);
+    }
+
+    #[test]
+    fn test_repeated_references() {
+        // A chain of nodes, each referring to its only child four times, so the value is
+        // multiplied by 4 at each level. Following each reference separately would take 4^25
+        // steps.
+        let depth = 25;
+        let mut nums = Vec::new();
+        for _ in 0..depth {
+            nums.extend(&[1, 4]);
+        }
+        nums.extend(&[0, 1, 1]);
+        for _ in 0..depth {
+            nums.extend(&[1, 1, 1, 1]);
+        }
+
+        let p = Parsed::parse(&nums).unwrap();
+        assert_eq!(p.value(), 1 << (2 * depth));
+        assert_eq!(p.sum_metadata(), 1 + 4 * depth);
+    }
+
+    #[test]
+    fn test_solve() {
+        let input = "2 3 0 3 10 11 12 1 1 0 1 99 2 1 1 2\n";
+        assert_eq!(solve(input).unwrap(), ("138".to_owned(), "66".to_owned()));
+
+        assert!(solve("2 3 0 x").is_err());
+        // Missing the root's last metadata entry
+        assert!(solve("2 3 0 3 10 11 12 1 1 0 1 99 2 1 1").is_err());
+        assert!(solve("0 1 5 7").is_err());
+    }
+}
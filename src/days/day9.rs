@@ -0,0 +1,265 @@
+/// The circle of marbles, stored as a doubly-linked ring.
+///
+/// Marbles are numbered consecutively, so marble `n` lives at index `n` of
+/// `links`, holding the `(counter-clockwise, clockwise)` neighbors of that
+/// marble. Removed marbles are simply unlinked.
+pub struct Game {
+    links: Vec<(usize, usize)>,
+    current: usize,
+    marble: i64,
+    scores: Vec<i64>,
+}
+
+impl Game {
+    pub fn new(players: usize) -> Game {
+        Game {
+            links: vec![(0, 0)],
+            current: 0,
+            marble: 1,
+            scores: vec![0; players],
+        }
+    }
+
+    fn clockwise(&self, ix: usize) -> usize {
+        self.links[ix].1
+    }
+
+    fn counter_clockwise(&self, ix: usize) -> usize {
+        self.links[ix].0
+    }
+
+    fn insert_after(&mut self, ix: usize, new: usize) {
+        let next = self.clockwise(ix);
+        self.links[new] = (ix, next);
+        self.links[ix].1 = new;
+        self.links[next].0 = new;
+    }
+
+    fn remove(&mut self, ix: usize) {
+        let (prev, next) = self.links[ix];
+        self.links[prev].1 = next;
+        self.links[next].0 = prev;
+        self.links[ix] = (ix, ix);
+    }
+
+    fn next(&mut self) {
+        let new = self.marble as usize;
+        self.links.push((new, new));
+
+        if self.marble % 23 == 0 {
+            let mut removed = self.current;
+            for _ in 0..7 {
+                removed = self.counter_clockwise(removed);
+            }
+            self.current = self.clockwise(removed);
+            self.remove(removed);
+
+            let player = (self.marble as usize) % (self.scores.len());
+            self.scores[player] += self.marble + removed as i64;
+            self.marble += 1;
+            return;
+        }
+
+        let left = self.clockwise(self.current);
+        self.insert_after(left, new);
+        self.current = new;
+        self.marble += 1;
+    }
+
+    pub fn play(&mut self, rounds: usize) {
+        for _ in 0..rounds {
+            self.next();
+        }
+    }
+
+    /// The `(player_index, score)` of the highest-scoring player, preferring
+    /// the lowest index in case of a tie.
+    pub fn winner(&self) -> Option<(usize, i64)> {
+        self.scores
+            .iter()
+            .cloned()
+            .enumerate()
+            .fold(None, |best, (ix, score)| match best {
+                Some((_, s)) if s >= score => best,
+                _ => Some((ix, score)),
+            })
+    }
+
+    /// Each player's score so far, by player index.
+    pub fn scores(&self) -> &[i64] {
+        &self.scores
+    }
+
+    pub fn score_of(&self, player: usize) -> i64 {
+        self.scores[player]
+    }
+
+    /// The number of marbles placed so far, not counting the starting marble.
+    pub fn marbles_played(&self) -> i64 {
+        self.marble - 1
+    }
+
+    /// The marble most recently placed, or the one clockwise of the last marble removed.
+    pub fn current_marble(&self) -> i64 {
+        self.current as i64
+    }
+
+    pub fn high_score(&self) -> i64 {
+        match self.winner() {
+            Some((_, n)) => n,
+            None => 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+
+    /// The original `VecDeque`-based implementation, kept as a reference.
+    fn deque_high_score(players: usize, rounds: usize) -> i64 {
+        let mut marbles: VecDeque<i64> = VecDeque::new();
+        marbles.push_back(0);
+        let mut scores = vec![0; players];
+
+        for marble in 1..=(rounds as i64) {
+            if marble % 23 == 0 {
+                for _ in 0..7 {
+                    let m = marbles.pop_front().unwrap();
+                    marbles.push_back(m);
+                }
+                let removed = marbles.pop_back().unwrap();
+                scores[(marble as usize) % players] += marble + removed;
+                continue;
+            }
+
+            if marbles.len() >= 2 {
+                for _ in 0..2 {
+                    let m = marbles.pop_back().unwrap();
+                    marbles.push_front(m);
+                }
+            }
+            marbles.push_back(marble);
+        }
+
+        scores.into_iter().max().unwrap_or(0)
+    }
+
+    #[test]
+    fn test_matches_deque() {
+        let games = [
+            (9, 25),
+            (10, 1618),
+            (13, 7999),
+            (17, 1104),
+            (21, 6111),
+            (30, 5807),
+        ];
+        for &(players, rounds) in &games {
+            let mut g = Game::new(players);
+            g.play(rounds);
+            assert_eq!(g.high_score(), deque_high_score(players, rounds));
+        }
+    }
+
+    #[test]
+    fn test_game() {
+        let mut g = Game::new(9);
+        g.play(25);
+
+        // Below is the order from the site, starting from marble 0 and
+        // going clockwise.
+        let mut order = vec![0];
+        let mut ix = g.clockwise(0);
+        while ix != 0 {
+            order.push(ix);
+            ix = g.clockwise(ix);
+        }
+        assert_eq!(
+            order,
+            vec!(
+                0, 16, 8, 17, 4, 18, 19, 2, 24, 20, 25, 10, 21, 5, 22, 11, 1, 12, 6, 13, 3, 14, 7,
+                15
+            )
+        );
+        assert_eq!(g.current, 25);
+        assert_eq!(g.high_score(), 32);
+    }
+
+    #[test]
+    fn test_winner() {
+        let mut g = Game::new(9);
+        g.play(25);
+        // Marble 23 is played by elf 5 in the example, which is index 5 here
+        // as marble `m` is played by `scores[m % players]`
+        assert_eq!(g.winner(), Some((5, 32)));
+
+        assert_eq!(Game::new(3).winner(), Some((0, 0)));
+        assert_eq!(Game::new(0).winner(), None);
+    }
+
+    #[test]
+    fn test_scores() {
+        let mut g = Game::new(9);
+        assert_eq!(g.marbles_played(), 0);
+        g.play(25);
+        assert_eq!(g.marbles_played(), 25);
+
+        let scores = g.scores();
+        assert_eq!(scores.len(), 9);
+        assert_eq!(g.score_of(5), 32);
+        for (player, &score) in scores.iter().enumerate() {
+            if player != 5 {
+                assert!(score < 32, "Player {} scored {}", player, score);
+            }
+        }
+        assert_eq!(scores.iter().sum::<i64>(), 32);
+    }
+
+    #[test]
+    fn test_resume() {
+        let mut whole = Game::new(9);
+        whole.play(25);
+
+        let mut parts = Game::new(9);
+        parts.play(10);
+        assert_eq!(parts.current_marble(), 10);
+        parts.play(15);
+        assert_eq!(parts.high_score(), whole.high_score());
+        assert_eq!(parts.scores(), whole.scores());
+        assert_eq!(parts.current_marble(), 25);
+        assert_eq!(parts.marbles_played(), 25);
+
+        // Stopping right on a scoring marble: 9 is removed, and marble 19 is now current
+        let mut g = Game::new(9);
+        g.play(23);
+        assert_eq!(g.current_marble(), 19);
+        g.play(2);
+        assert_eq!(g.high_score(), 32);
+        assert_eq!(g.current_marble(), 25);
+    }
+
+    #[test]
+    fn test_high_scores() {
+        let mut g = Game::new(10);
+        g.play(1618);
+        assert_eq!(g.high_score(), 8317);
+
+        g = Game::new(13);
+        g.play(7999);
+        assert_eq!(g.high_score(), 146_373);
+
+        g = Game::new(17);
+        g.play(1104);
+        assert_eq!(g.high_score(), 2764);
+
+        g = Game::new(21);
+        g.play(6111);
+        assert_eq!(g.high_score(), 54_718);
+
+        g = Game::new(30);
+        g.play(5807);
+        assert_eq!(g.high_score(), 37305);
+    }
+}
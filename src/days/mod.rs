@@ -0,0 +1,22 @@
+//! Days whose code lives in the library, so it can be shared with the `aoc` binary or the
+//! benchmarks as well as their own.
+
+pub mod day1;
+pub mod day11;
+pub mod day14;
+pub mod day15;
+pub mod day2;
+pub mod day8;
+pub mod day9;
+
+/// Solve both parts of the given day's puzzle. Fails if the input is invalid, or if that day
+/// isn't in the library yet.
+pub fn solve(day: u32, input: &str) -> Result<(String, String), failure::Error> {
+    let solver: fn(&str) -> Result<(String, String), failure::Error> = match day {
+        1 => day1::solve,
+        2 => day2::solve,
+        8 => day8::solve,
+        _ => failure::bail!("Day {} can't be run from here yet", day),
+    };
+    solver(input)
+}
@@ -1,8 +1,12 @@
 use text_io::try_scan;
 
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
 pub type Value = i64;
 
 #[derive(Debug, Copy, Clone, Hash, PartialEq, PartialOrd, Eq, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum OpCode {
     AddR,
     AddI,
@@ -68,14 +72,39 @@ impl OpCode {
 }
 
 #[derive(Debug, Copy, Clone, Hash, PartialEq, PartialOrd, Eq, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Instruction(pub OpCode, pub usize, pub usize, pub usize);
 
 #[derive(Debug, Clone, Hash, PartialEq, PartialOrd, Eq, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Register {
     pub values: Vec<Value>,
 }
 
 impl Register {
+    pub fn from_slice(values: &[Value]) -> Self {
+        Register {
+            values: values.to_vec(),
+        }
+    }
+
+    /// The registers that differ between self and other, as (index, self's value, other's
+    /// value). Registers past the end of the shorter one count as 0.
+    pub fn diff(&self, other: &Register) -> Vec<(usize, Value, Value)> {
+        let len = self.values.len().max(other.values.len());
+        (0..len)
+            .filter_map(|ix| {
+                let before = self.values.get(ix).cloned().unwrap_or(0);
+                let after = other.values.get(ix).cloned().unwrap_or(0);
+                if before == after {
+                    None
+                } else {
+                    Some((ix, before, after))
+                }
+            })
+            .collect()
+    }
+
     pub fn apply(&mut self, instr: Instruction) -> Value {
         let Instruction(op, a, b, c) = instr;
 
@@ -111,6 +140,8 @@ impl Register {
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Device {
     pub register: Register,
     pub bound: usize,
@@ -141,6 +172,18 @@ impl Device {
 
         true
     }
+
+    /// Snapshot the whole device, including its pointer and instructions, as JSON.
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> Result<String, failure::Error> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    /// Restore a device from a snapshot made by `to_json`.
+    #[cfg(feature = "serde")]
+    pub fn from_json(s: &str) -> Result<Self, failure::Error> {
+        Ok(serde_json::from_str(s)?)
+    }
 }
 
 pub fn parse_instructions<I, S>(lines: I) -> Result<(usize, Vec<Instruction>), failure::Error>
@@ -177,3 +220,72 @@ where
 
     Ok((pointer.unwrap_or(0), instructions))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Counts register 0 up to 100, then halts
+    const COUNTER_INPUT: &str = r#"
+        #ip 1
+        seti 0 0 0
+        addi 0 1 0
+        gtri 0 99 2
+        addr 2 1 1
+        seti 0 0 1
+    "#;
+
+    fn get_test_device() -> Device {
+        let (pointer, instructions) = parse_instructions(COUNTER_INPUT.split('\n')).unwrap();
+        Device::new(4, pointer, instructions)
+    }
+
+    fn run(d: &mut Device) -> usize {
+        let mut steps = 0;
+        while d.apply() {
+            steps += 1;
+        }
+        steps
+    }
+
+    #[test]
+    fn test_diff() {
+        let before = Register::from_slice(&[3, 2, 1, 1]);
+        let mut after = before.clone();
+        after.apply(Instruction(OpCode::AddR, 0, 1, 2));
+
+        assert_eq!(before.diff(&after), vec![(2, 1, 5)]);
+        assert_eq!(after.diff(&before), vec![(2, 5, 1)]);
+        assert_eq!(before.diff(&before), vec![]);
+        assert_eq!(
+            before.diff(&Register::from_slice(&[3, 2])),
+            vec![(2, 1, 0), (3, 1, 0)]
+        );
+    }
+
+    #[test]
+    fn test_run() {
+        let mut d = get_test_device();
+        run(&mut d);
+        assert_eq!(d.register.values[0], 100);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_json_round_trip() {
+        let mut uninterrupted = get_test_device();
+        let total = run(&mut uninterrupted);
+
+        let mut d = get_test_device();
+        for _ in 0..50 {
+            assert!(d.apply());
+        }
+        let snapshot = d.to_json().unwrap();
+        let mut resumed = Device::from_json(&snapshot).unwrap();
+        assert_eq!(resumed, d);
+
+        let rest = run(&mut resumed);
+        assert_eq!(50 + rest, total);
+        assert_eq!(resumed, uninterrupted);
+    }
+}
@@ -0,0 +1,69 @@
+/// The smallest rectangle containing a set of (x, y) points, with inclusive bounds.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct BoundingBox {
+    pub min: (i64, i64),
+    pub max: (i64, i64),
+}
+
+impl BoundingBox {
+    /// The bounding box of the given points, or None if there are none.
+    pub fn from_points<I: IntoIterator<Item = (i64, i64)>>(points: I) -> Option<Self> {
+        let mut iter = points.into_iter();
+        let first = iter.next()?;
+        let mut bbox = BoundingBox {
+            min: first,
+            max: first,
+        };
+        for (x, y) in iter {
+            bbox.min = (bbox.min.0.min(x), bbox.min.1.min(y));
+            bbox.max = (bbox.max.0.max(x), bbox.max.1.max(y));
+        }
+        Some(bbox)
+    }
+
+    /// The number of columns covered, counting both edges.
+    pub fn width(&self) -> i64 {
+        self.max.0 - self.min.0 + 1
+    }
+
+    /// The number of rows covered, counting both edges.
+    pub fn height(&self) -> i64 {
+        self.max.1 - self.min.1 + 1
+    }
+
+    /// Whether the point is inside the box, including its edges.
+    pub fn contains(&self, (x, y): (i64, i64)) -> bool {
+        self.min.0 <= x && x <= self.max.0 && self.min.1 <= y && y <= self.max.1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_points() {
+        let bbox = BoundingBox::from_points(vec![(3, -2), (-1, 4), (0, 0)]).unwrap();
+        assert_eq!(bbox.min, (-1, -2));
+        assert_eq!(bbox.max, (3, 4));
+        assert_eq!(bbox.width(), 5);
+        assert_eq!(bbox.height(), 7);
+
+        assert!(bbox.contains((-1, -2)));
+        assert!(bbox.contains((3, 4)));
+        assert!(bbox.contains((0, 0)));
+        assert!(!bbox.contains((4, 0)));
+        assert!(!bbox.contains((0, -3)));
+    }
+
+    #[test]
+    fn test_degenerate() {
+        let bbox = BoundingBox::from_points(vec![(7, 8)]).unwrap();
+        assert_eq!(bbox.min, bbox.max);
+        assert_eq!((bbox.width(), bbox.height()), (1, 1));
+        assert!(bbox.contains((7, 8)));
+        assert!(!bbox.contains((8, 8)));
+
+        assert_eq!(BoundingBox::from_points(vec![]), None);
+    }
+}
@@ -0,0 +1,73 @@
+use std::ops::{Add, Sub};
+
+// Locations are (y, x), so that they sort naturally into reading order. Unsigned coordinates
+// underflow at 0, so use signed ones near the edges.
+
+// The 4 neighbors of a location, in reading order: up, left, right, down.
+// Puzzles that break ties in reading order (e.g. day15's targeting) rely on this order.
+pub fn neighbors4<T>((y, x): (T, T)) -> [(T, T); 4]
+where
+    T: Copy + Add<Output = T> + Sub<Output = T> + From<u8>,
+{
+    let one = T::from(1);
+    [(y - one, x), (y, x - one), (y, x + one), (y + one, x)]
+}
+
+// The 8 neighbors of a location, including diagonals, in reading order.
+pub fn neighbors8<T>((y, x): (T, T)) -> [(T, T); 8]
+where
+    T: Copy + Add<Output = T> + Sub<Output = T> + From<u8>,
+{
+    let one = T::from(1);
+    [
+        (y - one, x - one),
+        (y - one, x),
+        (y - one, x + one),
+        (y, x - one),
+        (y, x + one),
+        (y + one, x - one),
+        (y + one, x),
+        (y + one, x + one),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_neighbors4() {
+        let ns = neighbors4((5i16, 10i16));
+        assert_eq!(ns, [(4, 10), (5, 9), (5, 11), (6, 10)]);
+
+        let mut sorted = ns;
+        sorted.sort();
+        assert_eq!(sorted, ns);
+    }
+
+    #[test]
+    fn test_neighbors8() {
+        let ns = neighbors8((5i64, 10i64));
+        assert_eq!(
+            ns,
+            [
+                (4, 9),
+                (4, 10),
+                (4, 11),
+                (5, 9),
+                (5, 11),
+                (6, 9),
+                (6, 10),
+                (6, 11)
+            ]
+        );
+
+        let mut sorted = ns;
+        sorted.sort();
+        assert_eq!(sorted, ns);
+
+        for n in &neighbors4((5i64, 10i64)) {
+            assert!(ns.contains(n));
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use std::fs::File;
+use std::io;
+use std::io::prelude::*;
+use std::io::BufReader;
+use std::path::Path;
+
+/// The trimmed, non-empty lines of a string. Test inputs are written indented, starting on the
+/// line after the opening quote, so this drops the indentation and blank lines around them.
+pub fn from_str(s: &str) -> impl Iterator<Item = &str> {
+    s.lines().map(str::trim).filter(|l| !l.is_empty())
+}
+
+/// The trimmed, non-empty lines of a file.
+pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Vec<String>, io::Error> {
+    let buf_reader = BufReader::new(File::open(path)?);
+    let mut lines = Vec::new();
+    for line in buf_reader.lines() {
+        let line = line?;
+        let trimmed = line.trim();
+        if !trimmed.is_empty() {
+            lines.push(trimmed.to_owned());
+        }
+    }
+    Ok(lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_str() {
+        let s = r#"
+
+            first line
+              second line
+
+            third
+        "#;
+        let lines: Vec<&str> = from_str(s).collect();
+        assert_eq!(lines, vec!["first line", "second line", "third"]);
+
+        assert_eq!(from_str("").count(), 0);
+        assert_eq!(from_str("\n  \n\n").count(), 0);
+        assert_eq!(
+            from_str("no newlines").collect::<Vec<_>>(),
+            vec!["no newlines"]
+        );
+    }
+
+    #[test]
+    fn test_from_file() {
+        let lines = from_file("inputs/day18.txt").unwrap();
+        assert_eq!(lines.len(), 50);
+        assert!(lines.iter().all(|l| !l.is_empty() && l.trim() == l));
+
+        assert!(from_file("inputs/no-such-day.txt").is_err());
+    }
+}
@@ -1,2 +1,7 @@
+pub mod days;
 pub mod device;
+pub mod geom;
+pub mod grid;
+pub mod input;
 pub mod parse;
+pub mod pathfind;
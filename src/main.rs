@@ -0,0 +1,38 @@
+#![warn(clippy::all)]
+
+use clap::{App, Arg};
+use std::fs;
+
+fn main() -> Result<(), failure::Error> {
+    let matches = App::new("Advent of Code 2018")
+        .arg(
+            Arg::with_name("day")
+                .short("d")
+                .long("day")
+                .value_name("DAY")
+                .required(true)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("input")
+                .short("i")
+                .long("input")
+                .value_name("INPUT")
+                .help("Defaults to inputs/dayN.txt")
+                .takes_value(true),
+        )
+        .get_matches();
+
+    let day: u32 = matches.value_of("day").unwrap().parse()?;
+    let default_path = format!("inputs/day{}.txt", day);
+    let input_path = matches.value_of("input").unwrap_or(&default_path);
+
+    eprintln!("Using input {}", input_path);
+
+    let input = fs::read_to_string(input_path)?;
+    let (part1, part2) = aoc::days::solve(day, &input)?;
+    println!("Part 1: {}", part1);
+    println!("Part 2: {}", part2);
+
+    Ok(())
+}
@@ -0,0 +1,243 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::hash::Hash;
+use std::ops::Add;
+
+/// A best-first search from a single start node, run one node at a time, for searches that
+/// need to watch or prune it as it goes. `astar` runs one to the end.
+///
+/// Among equally promising nodes, the one furthest along is tried first, as it's closest to
+/// finishing, and then the smallest, so tie-breaks (e.g. reading order) can be set by the
+/// node's `Ord`. A node keeps the first of equally cheap paths found to it.
+#[derive(Debug, Clone)]
+pub struct AStar<N, C> {
+    // node -> (cheapest cost found, previous node)
+    came_from: HashMap<N, (C, Option<N>)>,
+    // (Expected total cost, cost so far, node)
+    frontier: BinaryHeap<(Reverse<C>, C, Reverse<N>)>,
+}
+
+impl<N, C> AStar<N, C>
+where
+    N: Clone + Eq + Hash + Ord,
+    C: Copy + Default + Ord + Add<Output = C>,
+{
+    /// Start a search at the given node, at a cost of zero.
+    pub fn new(start: N) -> Self {
+        let zero = C::default();
+        let mut came_from = HashMap::new();
+        came_from.insert(start.clone(), (zero, None));
+        let mut frontier = BinaryHeap::new();
+        frontier.push((Reverse(zero), zero, Reverse(start)));
+
+        AStar {
+            came_from,
+            frontier,
+        }
+    }
+
+    /// Queue `node`, reached from `prev` at a total cost of `cost`, with `estimate` a lower
+    /// bound on the cost left from it to a goal. Returns false, leaving it out, if the node
+    /// was already reached as cheaply.
+    pub fn push(&mut self, node: N, cost: C, estimate: C, prev: N) -> bool {
+        if let Some(&(existing, _)) = self.came_from.get(&node) {
+            if existing <= cost {
+                return false;
+            }
+        }
+        self.came_from.insert(node.clone(), (cost, Some(prev)));
+        self.frontier
+            .push((Reverse(cost + estimate), cost, Reverse(node)));
+        true
+    }
+
+    /// The next node to expand and the cost to reach it, or None once the frontier is empty.
+    pub fn pop(&mut self) -> Option<(N, C)> {
+        while let Some((_, cost, Reverse(node))) = self.frontier.pop() {
+            if self.came_from[&node].0 < cost {
+                // A cheaper path here was found after this one was queued
+                continue;
+            }
+            return Some((node, cost));
+        }
+        None
+    }
+
+    /// The next entry in the frontier as (expected total cost, cost so far, node), without
+    /// removing it. It may be one `pop` will skip.
+    pub fn peek(&self) -> Option<(C, C, &N)> {
+        self.frontier
+            .peek()
+            .map(|(Reverse(expected), cost, Reverse(node))| (*expected, *cost, node))
+    }
+
+    /// Every entry in the frontier, as in `peek`, in no particular order.
+    pub fn frontier(&self) -> impl Iterator<Item = (C, C, &N)> {
+        self.frontier
+            .iter()
+            .map(|(Reverse(expected), cost, Reverse(node))| (*expected, *cost, node))
+    }
+
+    /// The number of entries in the frontier, including any `pop` will skip.
+    pub fn frontier_len(&self) -> usize {
+        self.frontier.len()
+    }
+
+    /// The number of nodes reached so far.
+    pub fn seen_len(&self) -> usize {
+        self.came_from.len()
+    }
+
+    /// Every node reached so far, with the cheapest cost found to it.
+    pub fn costs(&self) -> impl Iterator<Item = (&N, C)> {
+        self.came_from.iter().map(|(node, &(cost, _))| (node, cost))
+    }
+
+    /// The cheapest cost found to the node so far, if it's been reached.
+    pub fn cost(&self, node: &N) -> Option<C> {
+        self.came_from.get(node).map(|&(cost, _)| cost)
+    }
+
+    /// The cheapest path found to the node, as each node along it from the start to `node`
+    /// inclusive with the cost to reach it, if the node's been reached.
+    pub fn path(&self, node: &N) -> Option<Vec<(N, C)>> {
+        let mut last = node.clone();
+        let (mut cost, mut prev) = self.came_from.get(&last)?.clone();
+        let mut path = vec![];
+        loop {
+            path.push((last, cost));
+            last = match prev {
+                None => break,
+                Some(p) => p,
+            };
+            let (c, p) = self.came_from[&last].clone();
+            cost = c;
+            prev = p;
+        }
+        path.reverse();
+        Some(path)
+    }
+}
+
+/// Find the cheapest path from start to a node passing goal, returning its cost and the nodes
+/// along it, from start to goal inclusive.
+///
+/// neighbors gives each node reachable in one step, with the cost of that step, and heuristic
+/// a lower bound on the cost left to reach a goal; with a heuristic of zero, this is Dijkstra's
+/// algorithm. Ties are broken as in `AStar`, so tie-breaks (e.g. reading order) can be set by
+/// the node's Ord and the order neighbors are returned in.
+pub fn astar<N, C, FN, FH, FG, I>(
+    start: N,
+    mut goal: FG,
+    mut neighbors: FN,
+    mut heuristic: FH,
+) -> Option<(C, Vec<N>)>
+where
+    N: Clone + Eq + Hash + Ord,
+    C: Copy + Default + Ord + Add<Output = C>,
+    FN: FnMut(&N) -> I,
+    FH: FnMut(&N) -> C,
+    FG: FnMut(&N) -> bool,
+    I: IntoIterator<Item = (N, C)>,
+{
+    let mut search = AStar::new(start);
+
+    while let Some((node, cost)) = search.pop() {
+        if goal(&node) {
+            let path = search.path(&node)?;
+            return Some((cost, path.into_iter().map(|(n, _)| n).collect()));
+        }
+
+        for (next, step) in neighbors(&node) {
+            let estimate = heuristic(&next);
+            search.push(next, cost + step, estimate, node.clone());
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // (y, x) in a grid, with '#' for walls, and the cost of entering a square as its digit
+    fn grid_neighbors(grid: &[&str], (y, x): (usize, usize)) -> Vec<((usize, usize), i64)> {
+        let mut found = Vec::new();
+        let candidates = [
+            (y.wrapping_sub(1), x),
+            (y, x.wrapping_sub(1)),
+            (y, x + 1),
+            (y + 1, x),
+        ];
+        for &(ny, nx) in &candidates {
+            let c = grid.get(ny).and_then(|row| row.as_bytes().get(nx));
+            if let Some(&c) = c {
+                if c != b'#' {
+                    found.push(((ny, nx), i64::from(c - b'0')));
+                }
+            }
+        }
+        found
+    }
+
+    #[test]
+    fn test_astar() {
+        let grid = ["1111", "1#91", "1#11", "1111"];
+        let goal: (usize, usize) = (2, 2);
+        let manhattan = |&(y, x): &(usize, usize)| {
+            ((y as i64) - (goal.0 as i64)).abs() + ((x as i64) - (goal.1 as i64)).abs()
+        };
+
+        let (cost, path) = astar(
+            (0, 0),
+            |&n| n == goal,
+            |&n| grid_neighbors(&grid, n),
+            manhattan,
+        )
+        .unwrap();
+        assert_eq!(cost, 6);
+        assert_eq!(path.first(), Some(&(0, 0)));
+        assert_eq!(path.last(), Some(&goal));
+        assert_eq!(path.len(), 7);
+
+        // Dijkstra finds the same cost
+        let (cost, _) =
+            astar((0, 0), |&n| n == goal, |&n| grid_neighbors(&grid, n), |_| 0).unwrap();
+        assert_eq!(cost, 6);
+
+        // Walled off
+        let grid = ["11#1", "1#11"];
+        let found = astar(
+            (0, 0),
+            |&n| n == (0, 3),
+            |&n| grid_neighbors(&grid, n),
+            |_| 0,
+        );
+        assert_eq!(found, None);
+    }
+
+    #[test]
+    fn test_stepwise() {
+        let grid = ["1111", "1#91", "1#11", "1111"];
+        let mut search = AStar::new((0, 0));
+        while let Some((node, cost)) = search.pop() {
+            for (next, step) in grid_neighbors(&grid, node) {
+                search.push(next, cost + step, 0, node);
+            }
+        }
+
+        // Every open square is reached
+        assert_eq!(search.seen_len(), 14);
+        assert_eq!(search.frontier_len(), 0);
+        assert_eq!(search.cost(&(1, 2)), Some(11));
+        assert_eq!(search.cost(&(1, 1)), None);
+
+        let path = search.path(&(2, 2)).unwrap();
+        assert_eq!(path.first(), Some(&((0, 0), 0)));
+        assert_eq!(path.last(), Some(&((2, 2), 6)));
+        for pair in path.windows(2) {
+            assert_eq!(pair[1].1, pair[0].1 + 1);
+        }
+    }
+}
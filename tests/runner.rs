@@ -0,0 +1,44 @@
+use std::env;
+use std::fs;
+use std::process::Command;
+
+fn run_aoc(args: &[&str]) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_aoc"))
+        .args(args)
+        .output()
+        .expect("Failed to run aoc")
+}
+
+// Run aoc on the given input, written to a temp file named for the test and this process, so
+// parallel and concurrent runs don't share it.
+fn run_aoc_on(test_name: &str, day: &str, input: &str) -> std::process::Output {
+    let name = format!("aoc_runner_{}_{}.txt", test_name, std::process::id());
+    let path = env::temp_dir().join(name);
+    fs::write(&path, input).unwrap();
+
+    let output = run_aoc(&["--day", day, "--input", path.to_str().unwrap()]);
+    fs::remove_file(&path).unwrap();
+    output
+}
+
+#[test]
+fn test_day1() {
+    let output = run_aoc_on("test_day1", "1", "+1\n-2\n+3\n+1\n");
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout, "Part 1: 3\nPart 2: 2\n");
+}
+
+#[test]
+fn test_invalid_input() {
+    let output = run_aoc_on("test_invalid_input", "1", "+1\nnot a number\n");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("not a number"), "{}", stderr);
+}
+
+#[test]
+fn test_unknown_day() {
+    let output = run_aoc(&["--day", "26", "--input", "Cargo.toml"]);
+    assert!(!output.status.success());
+}